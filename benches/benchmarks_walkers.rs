@@ -4,8 +4,7 @@ use randomwalks_lib::dp::DynamicPrograms;
 use randomwalks_lib::kernel::normal_dist::NormalDistGenerator;
 use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
 use randomwalks_lib::kernel::Kernel;
-use randomwalks_lib::walker::multi_step::MultiStepWalker;
-use randomwalks_lib::walker::standard::StandardWalker;
+use randomwalks_lib::walker::backtracking::BacktrackingWalker;
 use randomwalks_lib::walker::Walker;
 
 pub fn benchmark_walker_standard(c: &mut Criterion) {
@@ -16,13 +15,13 @@ pub fn benchmark_walker_standard(c: &mut Criterion) {
     let mut dp = DynamicProgramBuilder::new()
         .simple()
         .time_limit(400)
-        .kernel(kernel.clone())
+        .kernel(kernel)
         .build()
         .unwrap();
 
     dp.compute();
 
-    let walker = StandardWalker { kernel: kernel };
+    let walker = BacktrackingWalker;
 
     for qty in walk_qtys.iter() {
         group
@@ -41,16 +40,13 @@ pub fn benchmark_walker_multistep(c: &mut Criterion) {
     let mut dp = DynamicProgramBuilder::new()
         .simple()
         .time_limit(400)
-        .kernel(kernel.clone())
+        .kernel(kernel)
         .build()
         .unwrap();
 
     dp.compute();
 
-    let walker = MultiStepWalker {
-        max_step_size: 10,
-        kernel: kernel,
-    };
+    let walker = BacktrackingWalker;
 
     for qty in walk_qtys.iter() {
         group
@@ -10,7 +10,8 @@
 //! [`add_points()`](DatasetBuilder::add_points)
 //! - Add a line of points using [`line()`](DatasetBuilder::line)
 //! - Add points in a certain area using [`fill()`](DatasetBuilder::fill)
-//! - Add points to randomly generated locations using [`random()`](DatasetBuilder::random)
+//! - Add points to randomly generated locations using [`random()`](DatasetBuilder::random),
+//! which can be made reproducible using [`seed()`](DatasetBuilder::seed)
 //!
 //! [`ColumnAction`s](loader::ColumnAction) are used to define which column of the imported data
 //! (for CSV and Polars) contains which information, such as the X- and Y coordinates etc.
@@ -37,7 +38,7 @@
 //! ```
 //! use randomwalks_lib::dataset::builder::DatasetBuilder;
 //! use randomwalks_lib::dataset::loader::{ColumnAction, CoordinateType};
-//! use randomwalks_lib::walker::standard::StandardWalker;
+//! use randomwalks_lib::walker::backtracking::BacktrackingWalker;
 //!
 //! let dataset = DatasetBuilder::new()
 //!     .from_csv("dataset.csv")
@@ -73,7 +74,8 @@ use std::collections::HashMap;
 use anyhow::bail;
 #[cfg(feature = "polars_loading")]
 use polars::prelude::DataFrame;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use thiserror::Error;
 
 use crate::dataset::loader::csv::{CSVLoader, CSVLoaderOptions};
@@ -119,6 +121,7 @@ pub struct DatasetBuilder {
     column_actions: Vec<ColumnAction<String>>,
     coordinate_type: Option<CoordinateType>,
     points: Vec<Point>,
+    rng: StdRng,
 }
 
 impl DatasetBuilder {
@@ -241,18 +244,27 @@ impl DatasetBuilder {
         self
     }
 
+    /// Seeds the random number generator used by [`random()`](DatasetBuilder::random).
+    ///
+    /// This must be set before adding random points. If not set, the generator is seeded from the
+    /// operating system's source of randomness.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+
+        self
+    }
+
     /// Adds randomly positioned points to the dataset.
     ///
     /// This adds `qty` points with random locations to the dataset. All points are placed in
-    /// between `from` and `to`.
+    /// between `from` and `to`. Use [`seed()`](DatasetBuilder::seed) to make the locations
+    /// reproducible.
     pub fn random(mut self, qty: usize, from: XYPoint, to: XYPoint) -> Self {
         self.source = DatasetSource::Manual;
 
-        let mut rng = rand::thread_rng();
-
         for _ in 0..qty {
-            let x = rng.gen_range(from.x..to.x);
-            let y = rng.gen_range(from.y..to.y);
+            let x = self.rng.gen_range(from.x..to.x);
+            let y = self.rng.gen_range(from.y..to.y);
 
             self.points.push(Point::XY(xy!(x, y)));
         }
@@ -324,6 +336,7 @@ impl Default for DatasetBuilder {
             column_actions: Vec::new(),
             coordinate_type: None,
             points: Vec::new(),
+            rng: StdRng::from_entropy(),
         }
     }
 }
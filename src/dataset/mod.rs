@@ -80,7 +80,7 @@
 //! # use randomwalks_lib::dp::simple::DynamicProgram;
 //! # use randomwalks_lib::kernel::Kernel;
 //! # use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
-//! # use randomwalks_lib::walker::standard::StandardWalker;
+//! # use randomwalks_lib::walker::backtracking::BacktrackingWalker;
 //! #
 //! # let dataset = Dataset::new(CoordinateType::XY);
 //! # let dp = DynamicProgramBuilder::new()
@@ -89,7 +89,7 @@
 //! #     .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
 //! #     .build()
 //! #     .unwrap();
-//! # let walker = Box::new(StandardWalker);
+//! # let walker = Box::new(BacktrackingWalker);
 //! #
 //! let path = dataset.rw_between(&dp, walker, 0, 1, 400).unwrap();
 //! ```
@@ -110,7 +110,7 @@
 //! # use randomwalks_lib::dp::simple::DynamicProgram;
 //! # use randomwalks_lib::kernel::Kernel;
 //! # use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
-//! # use randomwalks_lib::walker::standard::StandardWalker;
+//! # use randomwalks_lib::walker::backtracking::BacktrackingWalker;
 //! #
 //! # let dataset = Dataset::new(CoordinateType::XY);
 //! # let dp = DynamicProgramBuilder::new()
@@ -119,7 +119,7 @@
 //! #     .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
 //! #     .build()
 //! #     .unwrap();
-//! # let walker = Box::new(StandardWalker);
+//! # let walker = Box::new(BacktrackingWalker);
 //! #
 //! let paths = DatasetWalksBuilder::new()
 //!     .dataset(&dataset)
@@ -134,7 +134,10 @@
 //! ```
 //!
 //! Also, the number of time steps can be computed automatically. See the documentation of the
-//! [`DatasetWalksBuilder`](DatasetWalksBuilder) for more information.
+//! [`DatasetWalksBuilder`](DatasetWalksBuilder) for more information. Setting a master seed using
+//! [`seed()`](walks_builder::DatasetWalksBuilder::seed) makes the generated walks reproducible.
+//! Walks are generated in parallel and walks that cannot be generated are handled according to an
+//! [`ErrorPolicy`](walks_builder::ErrorPolicy).
 
 pub mod builder;
 pub mod loader;
@@ -150,13 +153,14 @@ use pathfinding::prelude::{build_path, dijkstra_all};
 use plotters::prelude::*;
 use point::{Coordinates, GCSPoint, Point, XYPoint};
 use proj::Proj;
-use rand::Rng;
+use rand::RngCore;
 use thiserror::Error;
 use time::macros::format_description;
 use time::PrimitiveDateTime;
 
 use crate::dataset::loader::{CoordinateType, DatasetLoader};
 use crate::dp::{DynamicProgramPool, DynamicPrograms};
+use crate::lattice::Lattice;
 use crate::walk::Walk;
 use crate::walker::Walker;
 use crate::xy;
@@ -409,6 +413,15 @@ impl Dataset {
 
     /// Convert all GCS points in the dataset to XY points and normalize them to the range [from, to].
     pub fn convert_gcs_to_xy(&mut self, scale: f64) -> anyhow::Result<()> {
+        self.convert_gcs_to_lattice(scale, Lattice::Square)
+    }
+
+    /// Convert all GCS points in the dataset to XY points on the given [`Lattice`].
+    ///
+    /// On square lattices, the scaled coordinates are truncated towards zero, the same as
+    /// [`convert_gcs_to_xy()`](Dataset::convert_gcs_to_xy). On hexagonal lattices, each point is
+    /// mapped to the coordinates of the lattice field containing it.
+    pub fn convert_gcs_to_lattice(&mut self, scale: f64, lattice: Lattice) -> anyhow::Result<()> {
         if self.coordinate_type != CoordinateType::GCS {
             bail!("dataset is not in GCS coordinates");
         }
@@ -424,7 +437,13 @@ impl Dataset {
             let new = conv
                 .convert((point.x, point.y))
                 .context("point conversion failed")?;
-            let new = XYPoint::from(((new.0 * scale) as i64, (new.1 * scale) as i64));
+            let (x, y) = match lattice {
+                Lattice::Square | Lattice::SquareDiagonal => {
+                    ((new.0 * scale) as isize, (new.1 * scale) as isize)
+                }
+                Lattice::Hexagonal => lattice.from_cartesian((new.0 * scale, new.1 * scale)),
+            };
+            let new = XYPoint::from((x as i64, y as i64));
 
             datapoint.point = Point::XY(new);
         }
@@ -435,6 +454,12 @@ impl Dataset {
     }
 
     pub fn convert_xy_to_gcs(&mut self, scale: f64) -> anyhow::Result<()> {
+        self.convert_lattice_to_gcs(scale, Lattice::Square)
+    }
+
+    /// Convert all XY points in the dataset, which are interpreted as coordinates on the given
+    /// [`Lattice`], to GCS points.
+    pub fn convert_lattice_to_gcs(&mut self, scale: f64, lattice: Lattice) -> anyhow::Result<()> {
         if self.coordinate_type != CoordinateType::XY {
             bail!("dataset is not in XY coordinates");
         }
@@ -447,8 +472,9 @@ impl Dataset {
             let Point::XY(point) = datapoint.point.clone() else {
                 bail!("point not in XY coordinates");
             };
+            let (x, y) = lattice.to_cartesian(point.x as isize, point.y as isize);
             let new = GCSPoint::from(
-                conv.convert((point.x as f64 / scale, point.y as f64 / scale))
+                conv.convert((x / scale, y / scale))
                     .context("point conversion failed")?,
             );
 
@@ -460,6 +486,45 @@ impl Dataset {
         Ok(())
     }
 
+    /// Convert the dataset into tracks in form of [`Walk`]s, e.g. for fitting walk models.
+    ///
+    /// Consecutive datapoints are interpreted as consecutive positions of a track. If `split_by`
+    /// is `Some`, a separate track is created for each value of the given metadata key, e.g. for
+    /// each agent. Returns an error if the dataset is not in XY coordinates.
+    pub fn to_walks(&self, split_by: Option<&str>) -> anyhow::Result<Vec<Walk>> {
+        if self.coordinate_type != CoordinateType::XY {
+            bail!("dataset is not in XY coordinates");
+        }
+
+        let mut keys: Vec<Option<&String>> = Vec::new();
+        let mut walks: Vec<Walk> = Vec::new();
+
+        for datapoint in self.data.iter() {
+            let Point::XY(point) = datapoint.point else {
+                bail!("point not in XY coordinates");
+            };
+            let key = match split_by {
+                Some(split_by) => Some(
+                    datapoint
+                        .metadata
+                        .get(split_by)
+                        .context("found datapoint without split_by metadata key")?,
+                ),
+                None => None,
+            };
+
+            match keys.iter().position(|k| *k == key) {
+                Some(i) => walks[i].0.push(point),
+                None => {
+                    keys.push(key);
+                    walks.push(Walk(vec![point]));
+                }
+            }
+        }
+
+        Ok(walks)
+    }
+
     pub fn rw_between(
         &self,
         dp: &DynamicProgramPool,
@@ -469,6 +534,31 @@ impl Dataset {
         time_steps: usize,
         auto_scale: bool,
         extra_steps: usize,
+    ) -> anyhow::Result<Walk> {
+        self.rw_between_with_rng(
+            dp,
+            walker,
+            from,
+            to,
+            time_steps,
+            auto_scale,
+            extra_steps,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Generates a random walk like [`rw_between()`](Dataset::rw_between) drawing all random
+    /// numbers from `rng`.
+    pub fn rw_between_with_rng(
+        &self,
+        dp: &DynamicProgramPool,
+        walker: &Box<dyn Walker>,
+        from: usize,
+        to: usize,
+        time_steps: usize,
+        auto_scale: bool,
+        extra_steps: usize,
+        rng: &mut dyn RngCore,
     ) -> anyhow::Result<Walk> {
         let from = &self.get(from).context("from index out of bounds.")?.point;
         let to = &self.get(to).context("to index out of bounds.")?.point;
@@ -505,11 +595,12 @@ impl Dataset {
         }
 
         let walk = walker
-            .generate_path(
+            .generate_path_with_rng(
                 dp,
                 translated_to.x as isize,
                 translated_to.y as isize,
                 time_steps,
+                rng,
             )
             .context("error while generating random walk path")?;
 
@@ -726,10 +817,14 @@ impl Dataset {
                 );
             }
 
-            let mut rng = rand::thread_rng();
+            // Assign colors in the order of the sorted classes, so plots are reproducible
+            let mut classes: Vec<_> = class_colors.keys().cloned().collect();
+            classes.sort();
+
+            for (i, class) in classes.into_iter().enumerate() {
+                let (r, g, b) = Palette99::pick(i).rgb();
 
-            for color in class_colors.values_mut() {
-                *color = RGBColor(rng.gen(), rng.gen(), rng.gen());
+                class_colors.insert(class, RGBColor(r, g, b));
             }
 
             for datapoint in self.data.iter().skip(from).take(to) {
@@ -3,6 +3,7 @@
 use std::ops::{Add, Sub};
 
 use num::Signed;
+use serde::{Deserialize, Serialize};
 
 /// Specifies points that have an X- and Y-coordinate.
 pub trait Coordinates<T: Signed> {
@@ -82,7 +83,7 @@ impl ToString for GCSPoint {
 }
 
 /// A 2d-point in XY coordinate system.
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct XYPoint {
     pub x: i64,
     pub y: i64,
@@ -1,4 +1,9 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use anyhow::Context;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
 use thiserror::Error;
 use time::format_description::parse_borrowed;
 use time::macros::format_description;
@@ -9,7 +14,7 @@ use crate::dataset::point::Coordinates;
 use crate::dataset::Dataset;
 use crate::dp::DynamicProgramPool;
 use crate::walk::Walk;
-use crate::walker::Walker;
+use crate::walker::{walk_seed, Walker};
 
 #[derive(Error, Debug)]
 pub enum DatasetWalksBuilderError {
@@ -34,6 +39,42 @@ pub enum TimeStepsBy {
     None,
 }
 
+/// Defines how a [`DatasetWalksBuilder`] handles walks that cannot be generated.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Building fails on the first walk that cannot be generated.
+    #[default]
+    Abort,
+
+    /// Walks that cannot be generated are left out.
+    Skip,
+
+    /// Generating a walk is retried up to the given number of times before it is left out.
+    Retry(usize),
+}
+
+/// Summary of the walks generated between a pair of consecutive datapoints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairReport {
+    /// Index of the datapoint the walks start at.
+    pub from: usize,
+
+    /// Index of the datapoint the walks end at.
+    pub to: usize,
+
+    /// Number of time steps of the walks.
+    pub time_steps: usize,
+
+    /// Number of walks that have been generated.
+    pub generated: usize,
+
+    /// Number of walks that have been left out.
+    pub failed: usize,
+
+    /// Errors of all failed attempts, including attempts that succeeded after retrying.
+    pub errors: Vec<String>,
+}
+
 pub struct DatasetWalksBuilder<'a> {
     dataset: Option<&'a Dataset>,
     dp: Option<&'a DynamicProgramPool>,
@@ -45,6 +86,8 @@ pub struct DatasetWalksBuilder<'a> {
     time_format: Option<&'static str>,
     auto_scale: bool,
     extra_steps: usize,
+    seed: Option<u64>,
+    error_policy: ErrorPolicy,
 }
 
 impl<'a> Default for DatasetWalksBuilder<'a> {
@@ -60,6 +103,8 @@ impl<'a> Default for DatasetWalksBuilder<'a> {
             time_format: None,
             auto_scale: false,
             extra_steps: 0,
+            seed: None,
+            error_policy: ErrorPolicy::default(),
         }
     }
 }
@@ -164,7 +209,43 @@ impl<'a> DatasetWalksBuilder<'a> {
         self
     }
 
+    /// Sets a master seed from which the seed of each walk is derived using [`walk_seed()`].
+    ///
+    /// With a seed, the same configuration always generates the same walks. If not set, the
+    /// thread-local random number generator is used.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+
+        self
+    }
+
+    /// Sets the [`ErrorPolicy`] for walks that cannot be generated. Defaults to
+    /// [`ErrorPolicy::Abort`].
+    pub fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+
+        self
+    }
+
+    /// Generates the walks. For details see
+    /// [`build_with_report()`](DatasetWalksBuilder::build_with_report).
     pub fn build(self) -> anyhow::Result<Vec<Walk>> {
+        Ok(self.build_with_report()?.0)
+    }
+
+    /// Generates the walks and returns them together with a [`PairReport`] for each pair of
+    /// consecutive datapoints.
+    ///
+    /// All walks are generated in parallel. The walks are ordered by pair and the order as well as
+    /// the walks themselves only depend on the [`seed()`](DatasetWalksBuilder::seed). Walks that
+    /// cannot be generated are handled according to the
+    /// [`error_policy()`](DatasetWalksBuilder::error_policy).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the builder is misconfigured or, using [`ErrorPolicy::Abort`], if any
+    /// walk cannot be generated.
+    pub fn build_with_report(self) -> anyhow::Result<(Vec<Walk>, Vec<PairReport>)> {
         let Some(dataset) = self.dataset else {
             return Err(DatasetWalksBuilderError::NoDatasetSet)?;
         };
@@ -193,7 +274,9 @@ impl<'a> DatasetWalksBuilder<'a> {
             None => format_description!("[year]-[month]-[day] [hour]:[minute]:[second]").to_vec(),
         };
 
-        let mut walks = Vec::new();
+        // Compute the number of time steps of all pairs of consecutive datapoints
+
+        let mut pairs = Vec::new();
 
         for i in self.from..to {
             let time_steps = match self.time_steps {
@@ -241,23 +324,207 @@ impl<'a> DatasetWalksBuilder<'a> {
                 }
             };
 
-            for _ in 0..self.count {
-                walks.push(
-                    dataset
-                        .rw_between(
-                            dp,
-                            walker,
-                            i,
-                            i + 1,
-                            time_steps,
-                            self.auto_scale,
-                            self.extra_steps,
-                        )
-                        .context("could not generate walk")?,
-                );
+            pairs.push((i, time_steps));
+        }
+
+        // Generate all walks in parallel, each using its own seeded random number generator
+
+        let count = self.count;
+        let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let attempts = match self.error_policy {
+            ErrorPolicy::Retry(retries) => retries + 1,
+            _ => 1,
+        };
+        let first_failure = AtomicUsize::new(usize::MAX);
+
+        let results: Vec<(Option<Walk>, Vec<anyhow::Error>)> = (0..pairs.len() * count)
+            .into_par_iter()
+            .map(|task| {
+                // Walks after the first failure are not needed when aborting
+                if self.error_policy == ErrorPolicy::Abort
+                    && task > first_failure.load(Ordering::Relaxed)
+                {
+                    return (None, Vec::new());
+                }
+
+                let (i, time_steps) = pairs[task / count];
+                let mut rng = StdRng::seed_from_u64(walk_seed(seed, task as u64));
+                let mut errors = Vec::new();
+
+                for _ in 0..attempts {
+                    match dataset.rw_between_with_rng(
+                        dp,
+                        walker,
+                        i,
+                        i + 1,
+                        time_steps,
+                        self.auto_scale,
+                        self.extra_steps,
+                        &mut rng,
+                    ) {
+                        Ok(walk) => return (Some(walk), errors),
+                        Err(error) => errors.push(error),
+                    }
+                }
+
+                first_failure.fetch_min(task, Ordering::Relaxed);
+
+                (None, errors)
+            })
+            .collect();
+
+        // Collect walks in order and report failures per pair
+
+        let mut walks = Vec::new();
+        let mut reports = Vec::new();
+        let mut results = results.into_iter();
+
+        for (i, time_steps) in pairs {
+            let mut report = PairReport {
+                from: i,
+                to: i + 1,
+                time_steps,
+                generated: 0,
+                failed: 0,
+                errors: Vec::new(),
+            };
+
+            for (walk, errors) in results.by_ref().take(count) {
+                match walk {
+                    Some(walk) => {
+                        walks.push(walk);
+                        report.generated += 1;
+                    }
+                    None if errors.is_empty() => (),
+                    None => {
+                        report.failed += 1;
+
+                        if self.error_policy == ErrorPolicy::Abort {
+                            let error = errors.into_iter().next().unwrap();
+
+                            return Err(error.context(format!(
+                                "could not generate walk between datapoints {} and {}",
+                                i,
+                                i + 1
+                            )));
+                        }
+                    }
+                }
+
+                report
+                    .errors
+                    .extend(errors.iter().map(|error| format!("{:#}", error)));
             }
+
+            reports.push(report);
         }
 
-        Ok(walks)
+        Ok((walks, reports))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::builder::DatasetBuilder;
+    use crate::dataset::loader::CoordinateType;
+    use crate::dataset::point::{Point, XYPoint};
+    use crate::dataset::walks_builder::{DatasetWalksBuilder, ErrorPolicy};
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::DynamicPrograms;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::Kernel;
+    use crate::walker::backtracking::BacktrackingWalker;
+    use crate::walker::Walker;
+    use crate::xy;
+
+    #[test]
+    fn test_seeded_walks() {
+        let dataset = |seed| {
+            DatasetBuilder::new()
+                .coordinate_type(CoordinateType::XY)
+                .seed(seed)
+                .random(4, xy!(0, 0), xy!(6, 6))
+                .build()
+                .unwrap()
+        };
+
+        assert_eq!(dataset(7).data, dataset(7).data);
+
+        let dataset = dataset(7);
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(30)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let walker: Box<dyn Walker> = Box::new(BacktrackingWalker);
+        let walks = |seed| {
+            DatasetWalksBuilder::new()
+                .dataset(&dataset)
+                .dp(&dp)
+                .walker(&walker)
+                .count(3)
+                .time_steps(20)
+                .seed(seed)
+                .build()
+                .unwrap()
+        };
+
+        assert_eq!(walks(1).len(), 9);
+        assert_eq!(walks(1), walks(1));
+        assert_ne!(walks(1), walks(2));
+    }
+
+    #[test]
+    fn test_error_policy() {
+        // The last pair is too far apart for the dynamic program
+        let dataset = DatasetBuilder::new()
+            .coordinate_type(CoordinateType::XY)
+            .add_points(vec![
+                Point::XY(xy!(0, 0)),
+                Point::XY(xy!(3, 0)),
+                Point::XY(xy!(40, 0)),
+            ])
+            .build()
+            .unwrap();
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(30)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let walker: Box<dyn Walker> = Box::new(BacktrackingWalker);
+        let builder = |error_policy| {
+            DatasetWalksBuilder::new()
+                .dataset(&dataset)
+                .dp(&dp)
+                .walker(&walker)
+                .count(4)
+                .time_steps(20)
+                .seed(3)
+                .error_policy(error_policy)
+        };
+
+        assert!(builder(ErrorPolicy::Abort).build().is_err());
+
+        let (walks, reports) = builder(ErrorPolicy::Skip).build_with_report().unwrap();
+
+        assert_eq!(walks.len(), 4);
+        assert_eq!(walks, builder(ErrorPolicy::Skip).build().unwrap());
+        assert_eq!((reports[0].generated, reports[0].failed), (4, 0));
+        assert_eq!((reports[1].generated, reports[1].failed), (0, 4));
+        assert_eq!((reports[1].from, reports[1].to), (1, 2));
+        assert_eq!(reports[1].errors.len(), 4);
+
+        let (walks, reports) = builder(ErrorPolicy::Retry(2)).build_with_report().unwrap();
+
+        assert_eq!(walks.len(), 4);
+        assert_eq!(reports[1].errors.len(), 12);
     }
 }
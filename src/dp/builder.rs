@@ -63,6 +63,23 @@
 //! allows to set the probability of each field separately. A probability of `0.0` means that the
 //! field is not visited in any way, while a probability of `1.0` means that the field has its
 //! normal probability that was assigned to it while computing the dynamic program.
+//!
+//! # Kernel Validation
+//!
+//! Kernels that are not valid probability distributions silently corrupt the whole dynamic
+//! program. Using [`validate_kernels()`](DynamicProgramBuilder::validate_kernels), every kernel is
+//! checked using [`Kernel::validate()`](crate::kernel::Kernel::validate) before building, and
+//! [`DynamicProgramBuilderError::InvalidKernel`] is returned for the first invalid one.
+//!
+//! # Transition Model
+//!
+//! By default, the kernel of a step is chosen by the field type of the field that is entered.
+//! Using [`kernel_selection()`](DynamicProgramBuilder::kernel_selection), the kernel of the field
+//! that is left can be used instead. Steps from one field type into another can be made more or
+//! less likely using [`crossing_weight()`](DynamicProgramBuilder::crossing_weight), and
+//! [`normalize_transitions()`](DynamicProgramBuilder::normalize_transitions) redistributes the
+//! probability of each field's steps so that they sum up to `1.0`. For details see the
+//! [`transition`](crate::dp::transition) module.
 
 use std::collections::HashMap;
 
@@ -70,10 +87,15 @@ use num::Zero;
 use thiserror::Error;
 
 use crate::dataset::point::XYPoint;
+use crate::dp::kernel_field::KernelField;
 use crate::dp::simple::DynamicProgram;
+use crate::dp::step_selection::weights_from_covariates;
+use crate::dp::transition::{KernelSelection, TransitionModel};
 use crate::dp::{DynamicProgramPool, DynamicProgramType};
 use crate::kernel;
-use crate::kernel::Kernel;
+use crate::kernel::generator::KernelGeneratorError;
+use crate::kernel::{Kernel, KernelValidationError};
+use crate::lattice::Lattice;
 
 /// An error that can occur when using a [`DynamicProgramBuilder`].
 #[derive(Error, Debug)]
@@ -118,6 +140,58 @@ pub enum DynamicProgramBuilderError {
     /// out of range of the dynamic program's table.
     #[error("barriers must be inside the time limit range")]
     BarrierOutOfRange,
+
+    /// This error occurs when the [`Lattice`] of a kernel differs from the lattice set using
+    /// [`lattice()`](DynamicProgramBuilder::lattice) or from the lattices of the other kernels.
+    #[error("all kernels must be defined on the lattice of the dynamic program")]
+    LatticeMismatch,
+
+    /// This error occurs when kernel validation was enabled using
+    /// [`validate_kernels()`](DynamicProgramBuilder::validate_kernels) and the kernel of a field
+    /// type is not a valid probability distribution.
+    #[error("kernel of field type {field_type} is invalid: {source}")]
+    InvalidKernel {
+        field_type: usize,
+        source: KernelValidationError,
+    },
+
+    /// This error occurs when the habitat weights given using
+    /// [`habitat_weights()`](DynamicProgramBuilder::habitat_weights) or
+    /// [`habitat_covariates()`](DynamicProgramBuilder::habitat_covariates) do not match the size
+    /// of the dynamic program's table.
+    #[error("habitat weights must be of same size as DP table")]
+    WrongSizeOfHabitatWeights,
+
+    /// This error occurs when the habitat weights contain negative or non-finite values.
+    #[error("habitat weights must be finite and non-negative")]
+    InvalidHabitatWeights,
+
+    /// This error occurs when the number of covariates and coefficients given using
+    /// [`habitat_covariates()`](DynamicProgramBuilder::habitat_covariates) differ or when the
+    /// covariates are of different sizes.
+    #[error("each covariate requires a coefficient and all covariates must be of the same size")]
+    HabitatCovariatesMismatch,
+
+    /// This error occurs when a crossing weight given using
+    /// [`crossing_weight()`](DynamicProgramBuilder::crossing_weight) refers to a field type
+    /// without a kernel.
+    #[error("crossing weight refers to field type {0} which has no kernel")]
+    UnknownCrossingFieldType(usize),
+
+    /// This error occurs when a crossing weight is negative or not finite.
+    #[error("crossing weights must be finite and non-negative")]
+    InvalidCrossingWeight,
+
+    /// This error occurs when the vectors of a [`KernelField`] given using
+    /// [`kernel_field()`](DynamicProgramBuilder::kernel_field) do not match the size of the
+    /// dynamic program's table.
+    #[error("kernel field must be of same size as DP table")]
+    WrongSizeOfKernelField,
+
+    /// This error occurs when the kernels of a [`KernelField`] cannot be created, e.g. because
+    /// the kernels are not defined on a square lattice.
+    #[error("could not create kernel field: {0}")]
+    InvalidKernelField(KernelGeneratorError),
 }
 
 /// A builder used to create and initialize dynamic programs.
@@ -131,6 +205,14 @@ pub struct DynamicProgramBuilder {
     kernels: Option<Vec<(usize, Kernel)>>,
     field_types: Option<Vec<Vec<usize>>>,
     barriers: Vec<XYPoint>,
+    lattice: Option<Lattice>,
+    validate_kernels: bool,
+    habitat_weights: Option<Vec<Vec<f64>>>,
+    habitat_covariates: Option<(Vec<Vec<Vec<f64>>>, Vec<f64>)>,
+    kernel_selection: KernelSelection,
+    crossing_weights: Vec<(usize, usize, f64)>,
+    normalize_transitions: bool,
+    kernel_field: Option<KernelField>,
 }
 
 impl DynamicProgramBuilder {
@@ -188,6 +270,83 @@ impl DynamicProgramBuilder {
         self
     }
 
+    /// Sets the [`Lattice`] of the dynamic program. If not set, the lattice of the kernels is used.
+    pub fn lattice(mut self, lattice: Lattice) -> Self {
+        self.lattice = Some(lattice);
+
+        self
+    }
+
+    /// Enables validation of all kernels using [`Kernel::validate()`] when building the dynamic
+    /// program. The internal kernel used for barriers is not validated.
+    pub fn validate_kernels(mut self) -> Self {
+        self.validate_kernels = true;
+
+        self
+    }
+
+    /// Sets habitat-selection weights for each field, turning the dynamic program into a
+    /// step-selection dynamic program. The weights use the same layout as
+    /// [`field_types()`](DynamicProgramBuilder::field_types). For details see the
+    /// [`step_selection`](crate::dp::step_selection) module.
+    pub fn habitat_weights(mut self, weights: Vec<Vec<f64>>) -> Self {
+        self.habitat_weights = Some(weights);
+        self.habitat_covariates = None;
+
+        self
+    }
+
+    /// Sets habitat-selection weights computed as `exp(Σ_k β_k * c_k)` from a stack of covariates
+    /// `c_k` and coefficients `β_k`. For details see the
+    /// [`step_selection`](crate::dp::step_selection) module.
+    pub fn habitat_covariates(
+        mut self,
+        covariates: Vec<Vec<Vec<f64>>>,
+        coefficients: Vec<f64>,
+    ) -> Self {
+        self.habitat_covariates = Some((covariates, coefficients));
+        self.habitat_weights = None;
+
+        self
+    }
+
+    /// Sets whether the kernel of a step is chosen by the field type of the destination or of the
+    /// source. Defaults to [`KernelSelection::Destination`]. If a
+    /// [`kernel_field()`](DynamicProgramBuilder::kernel_field) is set,
+    /// [`KernelSelection::Source`] is always used.
+    pub fn kernel_selection(mut self, kernel_selection: KernelSelection) -> Self {
+        self.kernel_selection = kernel_selection;
+
+        self
+    }
+
+    /// Multiplies the probability of all steps from a field of type `from` into a field of type
+    /// `to` by `weight`. Steps that stay within a field type are affected if `from` and `to` are
+    /// equal.
+    pub fn crossing_weight(mut self, from: usize, to: usize, weight: f64) -> Self {
+        self.crossing_weights.push((from, to, weight));
+
+        self
+    }
+
+    /// Normalizes the probabilities of all steps leaving each field to sum up to `1.0`. This is
+    /// always done if habitat weights are set.
+    pub fn normalize_transitions(mut self) -> Self {
+        self.normalize_transitions = true;
+
+        self
+    }
+
+    /// Sets a [`KernelField`] which rotates the kernel of each field towards a target or along a
+    /// vector field. The kernels are treated as prototypes heading north and are always selected
+    /// by the field that is left. For details see the [`kernel_field`](crate::dp::kernel_field)
+    /// module.
+    pub fn kernel_field(mut self, kernel_field: KernelField) -> Self {
+        self.kernel_field = Some(kernel_field);
+
+        self
+    }
+
     /// Adds a single barrier to the dynamic program.
     pub fn add_single_barrier(mut self, at: XYPoint) -> Self {
         self.barriers.push(at);
@@ -231,6 +390,26 @@ impl DynamicProgramBuilder {
             return Err(DynamicProgramBuilderError::NoKernelsSet);
         };
 
+        let lattice = self
+            .lattice
+            .or(kernels.first().map(|(_, kernel)| kernel.lattice()))
+            .unwrap_or_default();
+
+        if kernels.iter().any(|(_, kernel)| kernel.lattice() != lattice) {
+            return Err(DynamicProgramBuilderError::LatticeMismatch);
+        }
+
+        if self.validate_kernels {
+            for (field_type, kernel) in kernels.iter() {
+                kernel
+                    .validate()
+                    .map_err(|source| DynamicProgramBuilderError::InvalidKernel {
+                        field_type: *field_type,
+                        source,
+                    })?;
+            }
+        }
+
         // Map field types to contiguous value range
 
         let mut kernels_mapped = Vec::new();
@@ -249,9 +428,47 @@ impl DynamicProgramBuilder {
             }
         }
 
+        // Expand kernels into the heading variants of the kernel field
+
+        let variants = match &self.kernel_field {
+            Some(field) => field.sectors.variants(),
+            None => 1,
+        };
+
+        if let Some(field) = &self.kernel_field {
+            if !field.covers(time_limit) {
+                return Err(DynamicProgramBuilderError::WrongSizeOfKernelField);
+            }
+
+            let mut kernels_expanded = Vec::with_capacity(kernels_mapped.len() * variants);
+
+            for kernel in kernels_mapped.iter() {
+                kernels_expanded.extend(
+                    field
+                        .kernels(kernel)
+                        .map_err(DynamicProgramBuilderError::InvalidKernelField)?,
+                );
+            }
+
+            let limit = time_limit as isize;
+
+            for x in 0..2 * time_limit + 1 {
+                for y in 0..2 * time_limit + 1 {
+                    let (fx, fy) = (x as isize - limit, y as isize - limit);
+
+                    field_types[x][y] =
+                        field_types[x][y] * variants + field.variant_at(fx, fy, time_limit);
+                }
+            }
+
+            kernels_mapped = kernels_expanded;
+            i = kernels_mapped.len();
+        }
+
         // Add barriers
 
-        let empty_kernel = kernel!(0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let empty_kernel =
+            kernel!(0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0).with_lattice(lattice);
         kernels_mapped.push(empty_kernel);
 
         for (x, y) in self.barriers.iter().map(|p| <(i64, i64)>::from(*p)) {
@@ -269,6 +486,79 @@ impl DynamicProgramBuilder {
             field_types[x][y] = i;
         }
 
+        // Compute habitat weights
+
+        let habitat_weights = match self.habitat_covariates {
+            Some((covariates, coefficients)) => Some(
+                weights_from_covariates(&covariates, &coefficients)
+                    .ok_or(DynamicProgramBuilderError::HabitatCovariatesMismatch)?,
+            ),
+            None => self.habitat_weights,
+        };
+
+        let habitat_weights = match habitat_weights {
+            Some(weights) => {
+                if weights.len() != 2 * time_limit + 1
+                    || weights.iter().any(|column| column.len() != 2 * time_limit + 1)
+                {
+                    return Err(DynamicProgramBuilderError::WrongSizeOfHabitatWeights);
+                }
+
+                if weights.iter().flatten().any(|w| !w.is_finite() || *w < 0.0) {
+                    return Err(DynamicProgramBuilderError::InvalidHabitatWeights);
+                }
+
+                Some(weights)
+            }
+            None => None,
+        };
+
+        // Create transition model
+
+        let mut crossing_weights = HashMap::new();
+
+        for (from, to, weight) in self.crossing_weights {
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(DynamicProgramBuilderError::InvalidCrossingWeight);
+            }
+
+            let mapped = |field_type: usize| {
+                field_type_map
+                    .get(&field_type)
+                    .copied()
+                    .ok_or(DynamicProgramBuilderError::UnknownCrossingFieldType(field_type))
+            };
+
+            let (from, to) = (mapped(from)? * variants, mapped(to)? * variants);
+
+            for a in 0..variants {
+                for b in 0..variants {
+                    crossing_weights.insert((from + a, to + b), weight);
+                }
+            }
+        }
+
+        let normalize = self.normalize_transitions || habitat_weights.is_some();
+        // Kernels of a kernel field are only normalized for the field whose heading they follow
+        let kernel_selection = match self.kernel_field {
+            Some(_) => KernelSelection::Source,
+            None => self.kernel_selection,
+        };
+
+        let mut transition = TransitionModel {
+            kernel_selection,
+            crossing_weights,
+            habitat_weights,
+            barrier: Some(i),
+            ..Default::default()
+        };
+
+        transition.index(&kernels_mapped);
+
+        if normalize {
+            transition.normalize(&kernels_mapped, &field_types);
+        }
+
         Ok(DynamicProgramPool::Single(DynamicProgram {
             table: vec![
                 vec![vec![Zero::zero(); 2 * time_limit + 1]; 2 * time_limit + 1];
@@ -277,6 +567,8 @@ impl DynamicProgramBuilder {
             time_limit,
             kernels: kernels_mapped,
             field_types,
+            lattice,
+            transition,
         }))
     }
 }
@@ -285,10 +577,12 @@ impl DynamicProgramBuilder {
 mod tests {
     use crate::dataset::point::XYPoint;
     use crate::dp::builder::{DynamicProgramBuilder, DynamicProgramBuilderError};
-    use crate::dp::DynamicProgramType;
+    use crate::dp::{DynamicProgramType, DynamicPrograms};
     use crate::kernel::correlated_rw::CorrelatedRwGenerator;
+    use crate::kernel::lattice_rw::LatticeRwGenerator;
     use crate::kernel::simple_rw::SimpleRwGenerator;
     use crate::kernel::Kernel;
+    use crate::lattice::Lattice;
     use crate::xy;
 
     #[test]
@@ -384,6 +678,70 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_lattice_mismatch() {
+        let dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .lattice(Lattice::Hexagonal)
+            .build();
+
+        assert!(matches!(
+            dp,
+            Err(DynamicProgramBuilderError::LatticeMismatch)
+        ));
+
+        let dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(
+                Kernel::from_generator(LatticeRwGenerator {
+                    lattice: Lattice::Hexagonal,
+                })
+                .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(dp.lattice(), Lattice::Hexagonal);
+    }
+
+    #[test]
+    fn test_validate_kernels() {
+        let valid = Kernel::from_generator(SimpleRwGenerator).unwrap();
+        let invalid = Kernel::try_from_value(3, 0.5).unwrap();
+
+        assert!(DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernels(vec![(0, valid.clone()), (1, invalid.clone())])
+            .build()
+            .is_ok());
+
+        let dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernels(vec![(0, valid.clone()), (1, invalid)])
+            .validate_kernels()
+            .build();
+
+        assert!(matches!(
+            dp,
+            Err(DynamicProgramBuilderError::InvalidKernel { field_type: 1, .. })
+        ));
+
+        // Barriers use an empty kernel internally, which must not be rejected
+        assert!(DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(valid)
+            .add_single_barrier(xy!(1, 1))
+            .validate_kernels()
+            .build()
+            .is_ok());
+    }
+
     #[test]
     // fn test_multiple_kernels_for_single() {
     //     let dp = DynamicProgramBuilder::new()
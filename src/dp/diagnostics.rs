@@ -0,0 +1,217 @@
+//! Provides per-layer diagnostics for dynamic programs.
+//!
+//! After a dynamic program has been computed, each of its layers (one per time step `t`) is a
+//! probability distribution over the positions a walk starting at the origin can reach in `t`
+//! steps. The [`LayerStatistics`] computed for each layer allow checking that a dynamic program
+//! behaves as expected without inspecting its tables by eye.
+//!
+//! - `mass` should stay at `1.0`. Lower values indicate leakage over the border of the table or
+//! into barriers, larger values indicate a kernel that is not normalized.
+//! - `mean_squared_displacement` can be compared to the analytical diffusion of a kernel, e.g.
+//! `0.8 * t` for the [`SimpleRwGenerator`](crate::kernel::simple_rw::SimpleRwGenerator).
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::dp::builder::DynamicProgramBuilder;
+//! use randomwalks_lib::dp::DynamicPrograms;
+//! use randomwalks_lib::kernel::Kernel;
+//! use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
+//!
+//! let mut dp = DynamicProgramBuilder::new()
+//!     .simple()
+//!     .time_limit(50)
+//!     .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+//!     .build()
+//!     .unwrap();
+//!
+//! dp.compute();
+//!
+//! let stats = dp.diagnostics();
+//!
+//! assert!((stats[10].mean_squared_displacement - 8.0).abs() < 1e-9);
+//! ```
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Statistics of a single layer of a dynamic program.
+///
+/// All moments are computed with respect to the normalized distribution of the layer, i.e. they
+/// are independent of `mass`.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerStatistics {
+    /// The time step of the layer.
+    pub t: usize,
+
+    /// The sum of all probabilities in the layer.
+    pub mass: f64,
+
+    /// The X-coordinate of the centre of mass.
+    pub center_x: f64,
+
+    /// The Y-coordinate of the centre of mass.
+    pub center_y: f64,
+
+    /// The mean squared displacement from the origin.
+    pub mean_squared_displacement: f64,
+
+    /// The variance in X-direction.
+    pub cov_xx: f64,
+
+    /// The covariance between X- and Y-direction.
+    pub cov_xy: f64,
+
+    /// The variance in Y-direction.
+    pub cov_yy: f64,
+
+    /// The Shannon entropy (in nats) of the occupancy distribution.
+    pub entropy: f64,
+
+    /// The largest Euclidean distance from the origin of any field with a non-zero probability.
+    pub support_radius: f64,
+}
+
+impl LayerStatistics {
+    /// Computes the statistics of a single layer.
+    ///
+    /// `layer` is indexed as `layer[x][y]` with the origin at `(time_limit, time_limit)`, which is
+    /// the layout used by [`DynamicProgram`](crate::dp::simple::DynamicProgram).
+    pub fn from_layer(layer: &[Vec<f64>], t: usize, time_limit: usize) -> Self {
+        let offset = time_limit as f64;
+        let mut stats = LayerStatistics {
+            t,
+            ..Default::default()
+        };
+
+        let (mut sum_x, mut sum_y) = (0.0, 0.0);
+        let (mut sum_xx, mut sum_xy, mut sum_yy) = (0.0, 0.0, 0.0);
+
+        for (i, column) in layer.iter().enumerate() {
+            for (j, &p) in column.iter().enumerate() {
+                if p == 0.0 {
+                    continue;
+                }
+
+                let x = i as f64 - offset;
+                let y = j as f64 - offset;
+
+                stats.mass += p;
+                sum_x += p * x;
+                sum_y += p * y;
+                sum_xx += p * x * x;
+                sum_xy += p * x * y;
+                sum_yy += p * y * y;
+                stats.support_radius = stats.support_radius.max((x * x + y * y).sqrt());
+            }
+        }
+
+        if stats.mass == 0.0 {
+            return stats;
+        }
+
+        stats.center_x = sum_x / stats.mass;
+        stats.center_y = sum_y / stats.mass;
+        stats.mean_squared_displacement = (sum_xx + sum_yy) / stats.mass;
+        stats.cov_xx = sum_xx / stats.mass - stats.center_x * stats.center_x;
+        stats.cov_xy = sum_xy / stats.mass - stats.center_x * stats.center_y;
+        stats.cov_yy = sum_yy / stats.mass - stats.center_y * stats.center_y;
+
+        for &p in layer.iter().flatten() {
+            if p > 0.0 {
+                let p = p / stats.mass;
+
+                stats.entropy -= p * p.ln();
+            }
+        }
+
+        stats
+    }
+}
+
+/// Writes a list of [`LayerStatistics`] to a CSV file with a header row.
+pub fn write_csv<P: AsRef<Path>>(stats: &[LayerStatistics], path: P) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+
+    for layer in stats {
+        writer.serialize(layer)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::point::XYPoint;
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::diagnostics::LayerStatistics;
+    use crate::dp::DynamicPrograms;
+    use crate::kernel::normal_dist::NormalDistGenerator;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::Kernel;
+    use crate::xy;
+
+    #[test]
+    fn test_layer_statistics_single_point() {
+        let mut layer = vec![vec![0.0; 5]; 5];
+        layer[3][1] = 1.0;
+
+        let stats = LayerStatistics::from_layer(&layer, 0, 2);
+
+        assert_eq!(stats.mass, 1.0);
+        assert_eq!((stats.center_x, stats.center_y), (1.0, -1.0));
+        assert_eq!(stats.mean_squared_displacement, 2.0);
+        assert_eq!(stats.cov_xx, 0.0);
+        assert_eq!(stats.entropy, 0.0);
+        assert_eq!(stats.support_radius, 2f64.sqrt());
+    }
+
+    #[test]
+    fn test_diagnostics_simple_rw() {
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(20)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let stats = dp.diagnostics();
+
+        assert_eq!(stats.len(), 21);
+
+        for layer in stats.iter() {
+            let t = layer.t as f64;
+
+            assert!((layer.mass - 1.0).abs() < 1e-9);
+            assert!(layer.center_x.abs() < 1e-9 && layer.center_y.abs() < 1e-9);
+            assert!((layer.mean_squared_displacement - 0.8 * t).abs() < 1e-9);
+            assert!((layer.cov_xx - 0.4 * t).abs() < 1e-9);
+            assert!(layer.cov_xy.abs() < 1e-9);
+            assert!(layer.support_radius <= t);
+        }
+    }
+
+    #[test]
+    fn test_diagnostics_normal_dist() {
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(20)
+            .kernel(Kernel::from_generator(NormalDistGenerator::new(1.0, 7, xy!(0, 0))).unwrap())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let stats = dp.diagnostics();
+        let kernel_msd = stats[1].mean_squared_displacement;
+
+        // Variances of independent steps add up, as long as no mass leaves the table
+        assert!((stats[5].mean_squared_displacement - 5.0 * kernel_msd).abs() < 1e-6);
+        assert!(stats[5].entropy > stats[1].entropy);
+    }
+}
@@ -0,0 +1,318 @@
+//! Provides position-dependent kernels for target-directed movement.
+//!
+//! A [`KernelField`] assigns each field of a dynamic program a heading, either the bearing towards
+//! a target point, e.g. for homing, or the direction of a vector field, e.g. a wind or current
+//! raster. The kernel of each field type is treated as a prototype heading north and is rotated
+//! towards the heading of each field using [`Kernel::rotations()`].
+//!
+//! To keep memory bounded, headings are divided into [`HeadingSectors`] and only one rotated
+//! kernel per sector and field type is created. Fields whose heading vector is not longer than
+//! `min_magnitude`, e.g. fields close to the target or fields without current, use the undirected
+//! kernel of the stay variant, which is the mixture of all rotated kernels.
+//!
+//! Each step uses the kernel rotated towards the heading of the field it starts from, i.e. the
+//! dynamic program always uses [`KernelSelection::Source`](crate::dp::transition::KernelSelection)
+//! regardless of
+//! [`DynamicProgramBuilder::kernel_selection()`](crate::dp::builder::DynamicProgramBuilder::kernel_selection).
+//! Choosing kernels by the heading of the field that is entered would weight the steps leaving a
+//! field using different kernels, so that their probabilities no longer sum up to `1.0` and the
+//! dynamic program would lose or gain probability mass.
+//!
+//! Kernel fields are set using
+//! [`DynamicProgramBuilder::kernel_field()`](crate::dp::builder::DynamicProgramBuilder::kernel_field).
+//! Since the rotated kernels are part of the dynamic program's
+//! [`TransitionModel`](crate::dp::transition::TransitionModel), walkers sampling using
+//! [`DynamicProgram::transition_probability()`](crate::dp::simple::DynamicProgram::transition_probability),
+//! such as the [`BacktrackingWalker`](crate::walker::backtracking::BacktrackingWalker), sample
+//! from the same field.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::dataset::point::XYPoint;
+//! use randomwalks_lib::dp::builder::DynamicProgramBuilder;
+//! use randomwalks_lib::dp::kernel_field::KernelField;
+//! use randomwalks_lib::dp::DynamicPrograms;
+//! use randomwalks_lib::kernel::biased_rw::BiasedRwGenerator;
+//! use randomwalks_lib::kernel::{Direction, Kernel};
+//! use randomwalks_lib::xy;
+//!
+//! let prototype = Kernel::from_generator(BiasedRwGenerator {
+//!     probability: 0.6,
+//!     direction: Direction::North,
+//! })
+//! .unwrap();
+//!
+//! let mut dp = DynamicProgramBuilder::new()
+//!     .simple()
+//!     .time_limit(10)
+//!     .kernel(prototype)
+//!     .kernel_field(KernelField::towards(xy!(5, 0), 8).unwrap().with_min_magnitude(1.0))
+//!     .build()
+//!     .unwrap();
+//!
+//! dp.compute();
+//!
+//! assert!(dp.at(5, 0, 10, 0).unwrap() > dp.at(-5, 0, 10, 0).unwrap());
+//! ```
+
+use crate::dataset::point::XYPoint;
+use crate::kernel::correlated_sectors::HeadingSectors;
+use crate::kernel::generator::KernelGeneratorError;
+use crate::kernel::{Interpolation, Kernel};
+
+/// The heading of each field of a [`KernelField`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldHeading {
+    /// Each field heads towards the given target point.
+    Target(XYPoint),
+
+    /// Each field heads along the vector `(dx, dy)` given for it. North is the negative y
+    /// direction. The vectors use the same layout as
+    /// [`DynamicProgramBuilder::field_types()`](crate::dp::builder::DynamicProgramBuilder::field_types).
+    Vectors(Vec<Vec<(f64, f64)>>),
+}
+
+/// Position-dependent kernels rotated towards the heading of each field.
+///
+/// For details see the [`kernel_field`](crate::dp::kernel_field) module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelField {
+    pub heading: FieldHeading,
+    pub sectors: HeadingSectors,
+    pub interpolation: Interpolation,
+    pub grow: bool,
+    pub min_magnitude: f64,
+}
+
+impl KernelField {
+    /// Creates a kernel field heading towards `target` using `sectors` heading sectors.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KernelGeneratorError::InvalidParameter`] if `sectors` is zero.
+    pub fn towards(target: XYPoint, sectors: usize) -> Result<Self, KernelGeneratorError> {
+        Self::new(FieldHeading::Target(target), sectors)
+    }
+
+    /// Creates a kernel field heading along the given vectors using `sectors` heading sectors.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KernelGeneratorError::InvalidParameter`] if `sectors` is zero.
+    pub fn along(
+        vectors: Vec<Vec<(f64, f64)>>,
+        sectors: usize,
+    ) -> Result<Self, KernelGeneratorError> {
+        Self::new(FieldHeading::Vectors(vectors), sectors)
+    }
+
+    fn new(heading: FieldHeading, sectors: usize) -> Result<Self, KernelGeneratorError> {
+        if sectors == 0 {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "at least one heading sector is required".into(),
+            ));
+        }
+
+        Ok(Self {
+            heading,
+            sectors: HeadingSectors::new(sectors),
+            interpolation: Interpolation::default(),
+            grow: false,
+            min_magnitude: 0.0,
+        })
+    }
+
+    /// Sets the [`Interpolation`] used for rotating the kernels.
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+
+        self
+    }
+
+    /// Enlarges rotated kernels so that no probability mass is clipped, see
+    /// [`Kernel::rotate_by()`].
+    pub fn growing(mut self) -> Self {
+        self.grow = true;
+
+        self
+    }
+
+    /// Sets the length up to which heading vectors use the undirected kernel.
+    pub fn with_min_magnitude(mut self, min_magnitude: f64) -> Self {
+        self.min_magnitude = min_magnitude;
+
+        self
+    }
+
+    /// Returns the heading vector of the field at `(x, y)` of a dynamic program with the given
+    /// time limit.
+    pub fn heading_at(&self, x: isize, y: isize, time_limit: usize) -> (f64, f64) {
+        match &self.heading {
+            FieldHeading::Target(target) => {
+                ((target.x - x as i64) as f64, (target.y - y as i64) as f64)
+            }
+            FieldHeading::Vectors(vectors) => {
+                vectors[(time_limit as isize + x) as usize][(time_limit as isize + y) as usize]
+            }
+        }
+    }
+
+    /// Returns the heading variant of the field at `(x, y)` of a dynamic program with the given
+    /// time limit, see [`HeadingSectors::variant()`].
+    pub fn variant_at(&self, x: isize, y: isize, time_limit: usize) -> usize {
+        let (dx, dy) = self.heading_at(x, y, time_limit);
+
+        if dx.hypot(dy) <= self.min_magnitude {
+            self.sectors.sectors()
+        } else {
+            self.sectors.variant_of(dx, dy)
+        }
+    }
+
+    /// Returns the rotated kernels of all heading variants for a prototype kernel heading north.
+    pub fn kernels(&self, prototype: &Kernel) -> Result<Vec<Kernel>, KernelGeneratorError> {
+        prototype.rotations(self.sectors, self.interpolation, self.grow)
+    }
+
+    /// Returns whether the heading of every field of a dynamic program with the given time limit
+    /// is defined.
+    pub(crate) fn covers(&self, time_limit: usize) -> bool {
+        match &self.heading {
+            FieldHeading::Target(_) => true,
+            FieldHeading::Vectors(vectors) => {
+                vectors.len() == 2 * time_limit + 1
+                    && vectors
+                        .iter()
+                        .all(|column| column.len() == 2 * time_limit + 1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::point::XYPoint;
+    use crate::dp::builder::{DynamicProgramBuilder, DynamicProgramBuilderError};
+    use crate::dp::kernel_field::KernelField;
+    use crate::dp::transition::KernelSelection;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::biased_rw::BiasedRwGenerator;
+    use crate::kernel::generator::KernelGeneratorError;
+    use crate::kernel::{Direction, Kernel};
+    use crate::xy;
+
+    fn prototype() -> Kernel {
+        Kernel::from_generator(BiasedRwGenerator {
+            probability: 0.6,
+            direction: Direction::North,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_kernel_field_variants() {
+        let field = KernelField::towards(xy!(5, 0), 4).unwrap();
+
+        assert_eq!(field.variant_at(0, 0, 10), 1); // east
+        assert_eq!(field.variant_at(5, 3, 10), 0); // north
+        assert_eq!(field.variant_at(5, 0, 10), 4); // stay
+        assert_eq!(
+            field.clone().with_min_magnitude(1.5).variant_at(4, 0, 10),
+            4
+        );
+
+        let kernels = field.kernels(&prototype()).unwrap();
+
+        assert_eq!(kernels.len(), 5);
+        assert!((kernels[1].at(1, 0) - 0.6).abs() < 1e-12);
+        assert!((kernels[2].at(0, 1) - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_kernel_field_homing() {
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(prototype())
+            .kernel_field(KernelField::towards(xy!(5, 0), 8).unwrap())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        assert!(dp.at(5, 0, 10, 0).unwrap() > dp.at(-5, 0, 10, 0).unwrap());
+
+        let DynamicProgramPool::Single(dp) = dp else {
+            unreachable!();
+        };
+
+        // Steps from the origin head towards the target
+        assert!(dp.transition_probability(0, 0, 1, 0) > dp.transition_probability(0, 0, -1, 0));
+        assert!(dp.transition_probability(8, 0, 7, 0) > dp.transition_probability(8, 0, 9, 0));
+    }
+
+    #[test]
+    fn test_kernel_field_mass() {
+        for field in [
+            KernelField::towards(xy!(3, 0), 8).unwrap(),
+            KernelField::towards(xy!(-2, 4), 4).unwrap().growing(),
+        ] {
+            let mut dp = DynamicProgramBuilder::new()
+                .simple()
+                .time_limit(10)
+                .kernel(prototype())
+                .kernel_selection(KernelSelection::Destination)
+                .kernel_field(field)
+                .build()
+                .unwrap();
+
+            dp.compute();
+
+            for stats in dp.diagnostics() {
+                assert!((stats.mass - 1.0).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_kernel_field_vectors() {
+        // A current towards the south
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(5)
+            .kernel(prototype())
+            .kernel_field(KernelField::along(vec![vec![(0.0, 1.0); 11]; 11], 4).unwrap())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        assert!((dp.at(0, 1, 1, 0).unwrap() - 0.6).abs() < 1e-12);
+        assert!(dp.at(0, 3, 5, 0).unwrap() > dp.at(0, -3, 5, 0).unwrap());
+
+        let dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(5)
+            .kernel(prototype())
+            .kernel_field(KernelField::along(vec![vec![(0.0, 1.0); 3]; 3], 4).unwrap())
+            .build();
+
+        assert!(matches!(
+            dp,
+            Err(DynamicProgramBuilderError::WrongSizeOfKernelField)
+        ));
+    }
+
+    #[test]
+    fn test_kernel_field_without_sectors() {
+        assert!(matches!(
+            KernelField::towards(xy!(5, 0), 0),
+            Err(KernelGeneratorError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            KernelField::along(vec![vec![(0.0, 1.0); 11]; 11], 0),
+            Err(KernelGeneratorError::InvalidParameter(_))
+        ));
+    }
+}
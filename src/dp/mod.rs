@@ -79,10 +79,18 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use zstd::Decoder;
 
+use crate::dp::diagnostics::LayerStatistics;
 use crate::dp::simple::DynamicProgram;
+use crate::kernel::Kernel;
+use crate::lattice::Lattice;
 
 pub mod builder;
+pub mod diagnostics;
+pub mod kernel_field;
 pub mod simple;
+pub mod states;
+pub mod step_selection;
+pub mod transition;
 
 pub trait DynamicPrograms {
     fn limits(&self) -> (isize, isize);
@@ -91,8 +99,20 @@ pub trait DynamicPrograms {
 
     fn compute_parallel(&mut self);
 
+    /// Returns the [`Lattice`] the dynamic program is computed on.
+    fn lattice(&self) -> Lattice;
+
+    /// Returns the kernels of the dynamic program, indexed by field type. Barriers use an
+    /// additional kernel containing only zeros.
+    fn kernels(&self) -> &[Kernel];
+
+    /// Returns the field type of every field of the table, indexed by `x + time_limit` and
+    /// `y + time_limit`.
     fn field_types(&self) -> Vec<Vec<usize>>;
 
+    /// Computes [`LayerStatistics`] for every time step of the dynamic program.
+    fn diagnostics(&self) -> Vec<LayerStatistics>;
+
     #[cfg(feature = "plotting")]
     fn heatmap(&self, path: String, t: usize) -> anyhow::Result<()>;
 
@@ -373,12 +393,30 @@ impl DynamicPrograms for DynamicProgramPool {
         self.try_unwrap_mut().unwrap().compute_parallel()
     }
 
+    /// Wrapper for `SimpleDynamicProgram::lattice()`. Fails if called on a `DynamicProgramPool`
+    /// holding multiple dynamic programs.
+    fn lattice(&self) -> Lattice {
+        self.try_unwrap().unwrap().lattice()
+    }
+
+    /// Wrapper for `SimpleDynamicProgram::kernels()`. Fails if called on a `DynamicProgramPool`
+    /// holding multiple dynamic programs.
+    fn kernels(&self) -> &[Kernel] {
+        self.try_unwrap().unwrap().kernels()
+    }
+
     /// Wrapper for `SimpleDynamicProgram::field_types()`. Fails if called on a `DynamicProgramPool`
     /// holding multiple dynamic programs.
     fn field_types(&self) -> Vec<Vec<usize>> {
         self.try_unwrap().unwrap().field_types()
     }
 
+    /// Wrapper for `SimpleDynamicProgram::diagnostics()`. Fails if called on a
+    /// `DynamicProgramPool` holding multiple dynamic programs.
+    fn diagnostics(&self) -> Vec<LayerStatistics> {
+        self.try_unwrap().unwrap().diagnostics()
+    }
+
     /// Wrapper for `SimpleDynamicProgram::heatmap()`. Fails if called on a `DynamicProgramPool`
     /// holding multiple dynamic programs.
     #[cfg(feature = "plotting")]
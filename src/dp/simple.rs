@@ -14,6 +14,7 @@ use num::Zero;
 #[cfg(feature = "plotting")]
 use plotters::prelude::*;
 use rayon::prelude::*;
+use strum::IntoEnumIterator;
 use workerpool::thunk::{Thunk, ThunkWorker};
 use workerpool::Pool;
 #[cfg(feature = "saving")]
@@ -25,9 +26,12 @@ use {
 };
 
 use crate::dp::builder::DynamicProgramBuilder;
+use crate::dp::diagnostics::LayerStatistics;
+use crate::dp::transition::{self, TransitionModel};
 use crate::dp::{DynamicProgramPool, DynamicPrograms};
 use crate::kernel;
 use crate::kernel::Kernel;
+use crate::lattice::Lattice;
 
 #[derive(Clone)]
 pub struct DynamicProgram {
@@ -35,6 +39,8 @@ pub struct DynamicProgram {
     pub(crate) time_limit: usize,
     pub(crate) kernels: Vec<Kernel>,
     pub(crate) field_types: Vec<Vec<usize>>,
+    pub(crate) lattice: Lattice,
+    pub(crate) transition: TransitionModel,
 }
 
 impl DynamicProgram {
@@ -66,35 +72,73 @@ impl DynamicProgram {
     }
 
     fn apply_kernel_at(&mut self, x: isize, y: isize, t: usize) {
-        let field_type = self.field_type_at(x, y);
-        let kernel = self.kernels[field_type].clone();
+        let sum = apply_kernel(
+            &self.table[t - 1],
+            &self.kernels,
+            &self.field_types,
+            &self.transition,
+            self.limits(),
+            x,
+            y,
+        );
 
-        let ks = (kernel.size() / 2) as isize;
+        self.set(x, y, t, sum);
+    }
+
+    /// Returns the probability of a step from `(from_x, from_y)` to `(to_x, to_y)` as used by the
+    /// dynamic program, see [`TransitionModel`]. Returns `0.0` if either field lies outside of the
+    /// table.
+    pub fn transition_probability(
+        &self,
+        from_x: isize,
+        from_y: isize,
+        to_x: isize,
+        to_y: isize,
+    ) -> f64 {
         let (limit_neg, limit_pos) = self.limits();
-        let mut sum = 0.0;
 
-        for i in x - ks..=x + ks {
-            if i < limit_neg || i > limit_pos {
-                continue;
-            }
+        if [from_x, from_y, to_x, to_y]
+            .iter()
+            .any(|v| *v < limit_neg || *v > limit_pos)
+        {
+            return 0.0;
+        }
 
-            for j in y - ks..=y + ks {
-                if j < limit_neg || j > limit_pos {
-                    continue;
-                }
+        self.transition.probability(
+            &self.kernels,
+            &self.field_types,
+            (from_x, from_y),
+            (to_x, to_y),
+        )
+    }
 
-                // Kernel coordinates are inverted offset, i.e. -(i - x) and -(j - y)
-                let kernel_x = x - i;
-                let kernel_y = y - j;
+    /// Returns the [`TransitionModel`] of the dynamic program.
+    pub fn transition(&self) -> &TransitionModel {
+        &self.transition
+    }
 
-                sum += self.at(i, j, t - 1) * kernel.at(kernel_x, kernel_y);
-            }
-        }
+    /// Returns all fields from which `(x, y)` can possibly be reached in a single step. Only the
+    /// non-zero entries of sparse kernels are considered, see the
+    /// [`sparse`](crate::kernel::sparse) module. `(x, y)` must lie inside of the table, while the
+    /// returned fields may lie outside of it.
+    pub fn predecessors(&self, x: isize, y: isize) -> impl Iterator<Item = (isize, isize)> + '_ {
+        self.transition
+            .offsets(&self.field_types, (x, y))
+            .iter()
+            .map(move |(dx, dy)| (x - dx, y - dy))
+    }
 
-        self.set(x, y, t, sum);
+    /// Returns the largest distance in each axis that can be covered in a single step.
+    pub fn max_step_size(&self) -> usize {
+        transition::max_radius(&self.kernels) as usize
+    }
+
+    /// Computes the [`LayerStatistics`] of the layer at time step `t`.
+    pub fn layer_statistics(&self, t: usize) -> LayerStatistics {
+        LayerStatistics::from_layer(&self.table[t], t, self.time_limit)
     }
 
-    fn field_type_at(&self, x: isize, y: isize) -> usize {
+    pub(crate) fn field_type_at(&self, x: isize, y: isize) -> usize {
         let x = (self.time_limit as isize + x) as usize;
         let y = (self.time_limit as isize + y) as usize;
 
@@ -108,6 +152,16 @@ impl DynamicProgram {
         self.field_types[x][y] = val;
     }
 
+    /// Loads a dynamic program written by [`save()`](DynamicPrograms::save) including its
+    /// kernels and [`TransitionModel`], so walkers can sample from it.
+    ///
+    /// Files written before kernels were stored only contain the table. Walkers fail to sample
+    /// from those with [`WalkerError::InconsistentPath`](crate::walker::WalkerError).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or if it contains multiple field types but no
+    /// kernels.
     #[cfg(feature = "saving")]
     pub fn load(filename: String) -> anyhow::Result<DynamicProgramPool> {
         let file = File::open(filename)?;
@@ -148,6 +202,33 @@ impl DynamicProgram {
             }
         }
 
+        // Files written before lattices were introduced do not store a lattice
+        if decoder.read_exact(&mut buf).is_ok() {
+            dp.lattice = Lattice::iter()
+                .nth(u64::from_le_bytes(buf) as usize)
+                .context("invalid lattice in file")?;
+        }
+
+        // Files written before kernels were stored only contain the table, so walkers cannot
+        // sample from them
+        if decoder.read_exact(&mut buf).is_ok() {
+            let mut transitions = vec![0u8; u64::from_le_bytes(buf) as usize];
+            decoder.read_exact(&mut transitions)?;
+
+            (dp.kernels, dp.transition) =
+                serde_json::from_slice(&transitions).context("invalid kernels in file")?;
+            dp.transition.index(&dp.kernels);
+        }
+
+        if dp
+            .field_types
+            .iter()
+            .flatten()
+            .any(|t| *t >= dp.kernels.len())
+        {
+            bail!("file contains field types without kernels");
+        }
+
         Ok(DynamicProgramPool::Single(dp))
     }
 
@@ -159,6 +240,8 @@ impl DynamicProgram {
             time_limit: self.time_limit,
             kernels: self.kernels,
             field_types: self.field_types,
+            lattice: self.lattice,
+            transition: self.transition,
         }
     }
 }
@@ -197,6 +280,7 @@ impl DynamicPrograms for DynamicProgram {
         let (limit_neg, limit_pos) = self.limits();
         let kernels = Arc::new(RwLock::new(self.kernels.clone()));
         let field_types = Arc::new(RwLock::new(self.field_types.clone()));
+        let transition = Arc::new(self.transition.clone());
         let pool = Pool::<ThunkWorker<(Range<isize>, Range<isize>, Vec<Vec<f64>>)>>::new(10);
         let (tx, rx) = channel();
 
@@ -228,6 +312,7 @@ impl DynamicPrograms for DynamicProgram {
             for (x_range, y_range) in chunks.clone() {
                 let kernels = kernels.clone();
                 let field_types = field_types.clone();
+                let transition = transition.clone();
                 let table_old = table_old.clone();
 
                 pool.execute_to(
@@ -242,6 +327,7 @@ impl DynamicPrograms for DynamicProgram {
                                     &table_old.read().unwrap(),
                                     &kernels.read().unwrap(),
                                     &field_types.read().unwrap(),
+                                    &transition,
                                     (limit_neg, limit_pos),
                                     x,
                                     y,
@@ -282,10 +368,24 @@ impl DynamicPrograms for DynamicProgram {
     }
 
     #[cfg(not(tarpaulin_include))]
+    fn lattice(&self) -> Lattice {
+        self.lattice
+    }
+
+    fn kernels(&self) -> &[Kernel] {
+        &self.kernels
+    }
+
     fn field_types(&self) -> Vec<Vec<usize>> {
         self.field_types.clone()
     }
 
+    fn diagnostics(&self) -> Vec<LayerStatistics> {
+        (0..=self.time_limit)
+            .map(|t| self.layer_statistics(t))
+            .collect()
+    }
+
     #[cfg(not(tarpaulin_include))]
     #[cfg(feature = "plotting")]
     fn heatmap(&self, path: String, t: usize) -> anyhow::Result<()> {
@@ -373,57 +473,54 @@ impl DynamicPrograms for DynamicProgram {
 
         let mut encoder = encoder.auto_finish();
 
-        encoder.write(&(self.time_limit as u64).to_le_bytes())?;
+        encoder.write_all(&(self.time_limit as u64).to_le_bytes())?;
 
         for t in 0..=limit_pos as usize {
             for x in limit_neg..=limit_pos {
                 for y in limit_neg..=limit_pos {
-                    encoder.write(&self.at(x, y, t).to_le_bytes())?;
+                    encoder.write_all(&self.at(x, y, t).to_le_bytes())?;
                 }
             }
         }
 
         for x in limit_neg..=limit_pos {
             for y in limit_neg..=limit_pos {
-                encoder.write(&(self.field_type_at(x, y) as u64).to_le_bytes())?;
+                encoder.write_all(&(self.field_type_at(x, y) as u64).to_le_bytes())?;
             }
         }
 
+        let lattice = Lattice::iter().position(|l| l == self.lattice).unwrap();
+        encoder.write_all(&(lattice as u64).to_le_bytes())?;
+
+        let transitions = serde_json::to_vec(&(&self.kernels, &self.transition))?;
+        encoder.write_all(&(transitions.len() as u64).to_le_bytes())?;
+        encoder.write_all(&transitions)?;
+
         Ok(())
     }
 }
 
-fn apply_kernel(
+pub(crate) fn apply_kernel(
     table_old: &Vec<Vec<f64>>,
     kernels: &Vec<Kernel>,
     field_types: &Vec<Vec<usize>>,
+    transition: &TransitionModel,
     (limit_neg, limit_pos): (isize, isize),
     x: isize,
     y: isize,
 ) -> f64 {
-    let field_type = field_types[(limit_pos + x) as usize][(limit_pos + y) as usize];
-    let kernel = kernels[field_type].clone();
-
-    let ks = (kernel.size() / 2) as isize;
     let mut sum = 0.0;
 
-    for i in x - ks..=x + ks {
-        if i < limit_neg || i > limit_pos {
+    for &(dx, dy) in transition.offsets(field_types, (x, y)) {
+        let (i, j) = (x - dx, y - dy);
+
+        if i < limit_neg || i > limit_pos || j < limit_neg || j > limit_pos {
             continue;
         }
 
-        for j in y - ks..=y + ks {
-            if j < limit_neg || j > limit_pos {
-                continue;
-            }
-
-            // Kernel coordinates are inverted offset, i.e. -(i - x) and -(j - y)
-            let kernel_x = x - i;
-            let kernel_y = y - j;
+        let p = transition.probability(kernels, field_types, (i, j), (x, y));
 
-            sum += table_old[(limit_pos + i) as usize][(limit_pos + j) as usize]
-                * kernel.at(kernel_x, kernel_y);
-        }
+        sum += table_old[(limit_pos + i) as usize][(limit_pos + j) as usize] * p;
     }
 
     sum
@@ -443,6 +540,7 @@ impl PartialEq for DynamicProgram {
         self.time_limit == other.time_limit
             && self.table == other.table
             && self.field_types == other.field_types
+            && self.lattice == other.lattice
     }
 }
 
@@ -455,6 +553,8 @@ pub struct DynamicProgramLayerIterator {
     pub(crate) time_limit: usize,
     pub(crate) kernels: Vec<Kernel>,
     pub(crate) field_types: Vec<Vec<usize>>,
+    pub(crate) lattice: Lattice,
+    pub(crate) transition: TransitionModel,
 }
 
 impl Iterator for DynamicProgramLayerIterator {
@@ -479,6 +579,8 @@ impl Iterator for DynamicProgramLayerIterator {
                 time_limit: self.time_limit,
                 kernels: self.kernels.clone(),
                 field_types: self.field_types.clone(),
+                lattice: self.lattice,
+                transition: self.transition.clone(),
             });
 
             return Some(self.last_layer.clone());
@@ -554,10 +656,14 @@ pub fn compute_multiple_save_layered(dps: Vec<DynamicProgram>, path: String) {
 #[cfg(test)]
 mod tests {
     use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::simple::DynamicProgram;
+    use crate::dp::transition::KernelSelection;
     use crate::dp::{DynamicProgramPool, DynamicPrograms};
     use crate::kernel::biased_rw::BiasedRwGenerator;
     use crate::kernel::simple_rw::SimpleRwGenerator;
     use crate::kernel::{Direction, Kernel};
+    use crate::walker::backtracking::BacktrackingWalker;
+    use crate::walker::Walker;
 
     #[test]
     fn test_simple_dp_at() {
@@ -682,4 +788,63 @@ mod tests {
 
         assert_ne!(dp1, dp2);
     }
+
+    #[test]
+    #[cfg(feature = "saving")]
+    fn test_save_load() {
+        // Fields with x > 0 are crossed using jumps of two fields
+        let field_types: Vec<Vec<usize>> = (0..21)
+            .map(|x| vec![if x > 10 { 1 } else { 0 }; 21])
+            .collect();
+        let jumps = Kernel::from_entries(
+            2,
+            &[(2, 0, 0.25), (-2, 0, 0.25), (0, 2, 0.25), (0, -2, 0.25)],
+        )
+        .unwrap();
+
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernels(vec![
+                (0, Kernel::from_generator(SimpleRwGenerator).unwrap()),
+                (1, jumps),
+            ])
+            .field_types(field_types)
+            .kernel_selection(KernelSelection::Source)
+            .crossing_weight(0, 1, 0.1)
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let path = std::env::temp_dir().join("randomwalks_test_save_load.zst");
+        let filename = path.to_str().unwrap().to_string();
+
+        dp.save(filename.clone()).unwrap();
+        let loaded = DynamicProgram::load(filename).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Walkers sample from the kernels and transition model of the loaded dynamic program
+        for walk in BacktrackingWalker
+            .generate_paths_seeded(&loaded, 10, 5, 1, 9, 42)
+            .unwrap()
+        {
+            assert_eq!(walk.len(), 10);
+        }
+
+        let DynamicProgramPool::Single(dp) = dp else {
+            unreachable!();
+        };
+        let DynamicProgramPool::Single(loaded) = loaded else {
+            unreachable!();
+        };
+
+        assert_eq!(loaded, dp);
+        assert_eq!(loaded.kernels, dp.kernels);
+        assert_eq!(loaded.transition, dp.transition);
+        assert_eq!(
+            loaded.transition_probability(0, 0, 1, 0),
+            dp.transition_probability(0, 0, 1, 0)
+        );
+    }
 }
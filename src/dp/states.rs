@@ -0,0 +1,334 @@
+//! Provides dynamic programs with hidden behavioral states.
+//!
+//! Animals alternate between behaviors such as resting, foraging and travelling, each with its own
+//! movement characteristics. A [`StateDynamicProgram`] computes the probabilities of all fields
+//! together with the behavioral state the walk is in. Each state has its own
+//! [`DynamicProgram`], which defines the kernels, field types, barriers and
+//! [`TransitionModel`](crate::dp::transition::TransitionModel) used while being in that state.
+//!
+//! Before each step, the state switches according to a Markov transition matrix, where
+//! `switching[i][j]` is the probability of switching from state `i` to state `j`. The step is then
+//! made using the model of the new state, i.e.
+//!
+//! ```text
+//! p(x, y, t, j) = Σ_i Σ_(x', y') p(x', y', t - 1, i) * switching[i][j] * P_j((x', y') -> (x, y))
+//! ```
+//!
+//! At time step `0`, the walk is at the origin and its state is distributed according to the
+//! `initial` distribution.
+//!
+//! Walks together with their state sequences are generated using the
+//! [`StateWalker`](crate::walker::states::StateWalker).
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::dp::builder::DynamicProgramBuilder;
+//! use randomwalks_lib::dp::states::StateDynamicProgram;
+//! use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
+//! use randomwalks_lib::kernel::Kernel;
+//! use randomwalks_lib::kernel;
+//!
+//! let state = |kernel: Kernel| {
+//!     DynamicProgramBuilder::new()
+//!         .simple()
+//!         .time_limit(10)
+//!         .kernel(kernel)
+//!         .build()
+//!         .unwrap()
+//! };
+//!
+//! let resting = state(kernel!(0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0));
+//! let foraging = state(Kernel::from_generator(SimpleRwGenerator).unwrap());
+//!
+//! let mut dp = StateDynamicProgram::try_new(
+//!     vec![resting, foraging],
+//!     vec![vec![0.9, 0.1], vec![0.2, 0.8]],
+//!     vec![0.5, 0.5],
+//! )
+//! .unwrap();
+//!
+//! dp.compute();
+//!
+//! // Resting after switching from either state or foraging without moving
+//! assert!((dp.marginal_at(0, 0, 1) - (0.55 + 0.45 * 0.2)).abs() < 1e-12);
+//! ```
+
+use thiserror::Error;
+
+use crate::dp::simple::{apply_kernel, DynamicProgram};
+use crate::dp::DynamicProgramPool;
+use crate::kernel::SUM_TOLERANCE;
+
+/// An error that can occur when creating a [`StateDynamicProgram`].
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum StateDynamicProgramError {
+    /// This error occurs when no states are given.
+    #[error("at least one state is required")]
+    NoStates,
+
+    /// This error occurs when the dynamic program of a state holds multiple dynamic programs.
+    #[error("the dynamic program of state {0} must be a single dynamic program")]
+    MultipleDynamicPrograms(usize),
+
+    /// This error occurs when the dynamic programs of the states have different time limits.
+    #[error("the dynamic programs of all states must have the same time limit")]
+    TimeLimitMismatch,
+
+    /// This error occurs when the switching matrix is not square with one row per state, contains
+    /// negative or non-finite values or when a row does not sum up to `1.0`.
+    #[error("the switching matrix must be a stochastic matrix with one row per state")]
+    InvalidSwitchingMatrix,
+
+    /// This error occurs when the initial distribution does not contain one non-negative value per
+    /// state or does not sum up to `1.0`.
+    #[error("the initial distribution must contain one probability per state")]
+    InvalidInitialDistribution,
+}
+
+/// A dynamic program over fields and behavioral states.
+///
+/// For details see the [`states`](crate::dp::states) module.
+#[derive(Debug, Clone)]
+pub struct StateDynamicProgram {
+    pub(crate) states: Vec<DynamicProgram>,
+    pub(crate) switching: Vec<Vec<f64>>,
+    pub(crate) initial: Vec<f64>,
+    pub(crate) time_limit: usize,
+}
+
+impl StateDynamicProgram {
+    /// Creates a dynamic program from one dynamic program per state, the switching matrix and the
+    /// initial state distribution. The dynamic programs of the states do not need to be computed.
+    pub fn try_new(
+        states: Vec<DynamicProgramPool>,
+        switching: Vec<Vec<f64>>,
+        initial: Vec<f64>,
+    ) -> Result<Self, StateDynamicProgramError> {
+        let states = states
+            .into_iter()
+            .enumerate()
+            .map(|(i, dp)| match dp {
+                DynamicProgramPool::Single(dp) => Ok(dp),
+                _ => Err(StateDynamicProgramError::MultipleDynamicPrograms(i)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let Some(time_limit) = states.first().map(|dp| dp.time_limit) else {
+            return Err(StateDynamicProgramError::NoStates);
+        };
+
+        if states.iter().any(|dp| dp.time_limit != time_limit) {
+            return Err(StateDynamicProgramError::TimeLimitMismatch);
+        }
+
+        if switching.len() != states.len()
+            || switching
+                .iter()
+                .any(|row| row.len() != states.len() || !is_distribution(row))
+        {
+            return Err(StateDynamicProgramError::InvalidSwitchingMatrix);
+        }
+
+        if initial.len() != states.len() || !is_distribution(&initial) {
+            return Err(StateDynamicProgramError::InvalidInitialDistribution);
+        }
+
+        Ok(Self {
+            states,
+            switching,
+            initial,
+            time_limit,
+        })
+    }
+
+    /// Returns the number of states.
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns the dynamic program defining the movement in `state`.
+    pub fn state(&self, state: usize) -> &DynamicProgram {
+        &self.states[state]
+    }
+
+    /// Returns the probability of switching from state `from` to state `to` before a step.
+    pub fn switching_probability(&self, from: usize, to: usize) -> f64 {
+        self.switching[from][to]
+    }
+
+    pub fn time_limit(&self) -> usize {
+        self.time_limit
+    }
+
+    pub fn limits(&self) -> (isize, isize) {
+        (-(self.time_limit as isize), self.time_limit as isize)
+    }
+
+    /// Returns the probability of being at `(x, y)` in `state` at time step `t`.
+    pub fn at(&self, x: isize, y: isize, t: usize, state: usize) -> f64 {
+        self.states[state].at(x, y, t)
+    }
+
+    pub fn at_or(&self, x: isize, y: isize, t: usize, state: usize, default: f64) -> f64 {
+        self.states[state].at_or(x, y, t, default)
+    }
+
+    /// Returns the probability of being at `(x, y)` at time step `t` in any state.
+    pub fn marginal_at(&self, x: isize, y: isize, t: usize) -> f64 {
+        self.states.iter().map(|dp| dp.at(x, y, t)).sum()
+    }
+
+    pub fn compute(&mut self) {
+        let (limit_neg, limit_pos) = self.limits();
+        let size = 2 * self.time_limit + 1;
+
+        for (dp, initial) in self.states.iter_mut().zip(self.initial.iter()) {
+            for layer in dp.table.iter_mut() {
+                layer.iter_mut().for_each(|column| column.fill(0.0));
+            }
+
+            dp.set(0, 0, 0, *initial);
+        }
+
+        for t in 1..=self.time_limit {
+            for to in 0..self.states.len() {
+                // Probabilities after switching into the state before making the step
+                let mut switched = vec![vec![0.0; size]; size];
+
+                for (from, dp) in self.states.iter().enumerate() {
+                    let p = self.switching[from][to];
+
+                    if p == 0.0 {
+                        continue;
+                    }
+
+                    for (column, previous) in switched.iter_mut().zip(dp.table[t - 1].iter()) {
+                        for (value, previous) in column.iter_mut().zip(previous.iter()) {
+                            *value += p * previous;
+                        }
+                    }
+                }
+
+                let dp = &self.states[to];
+                let mut layer = vec![vec![0.0; size]; size];
+
+                for x in limit_neg..=limit_pos {
+                    for y in limit_neg..=limit_pos {
+                        layer[(limit_pos + x) as usize][(limit_pos + y) as usize] = apply_kernel(
+                            &switched,
+                            &dp.kernels,
+                            &dp.field_types,
+                            &dp.transition,
+                            (limit_neg, limit_pos),
+                            x,
+                            y,
+                        );
+                    }
+                }
+
+                self.states[to].table[t] = layer;
+            }
+        }
+    }
+}
+
+/// Returns whether all values are finite and non-negative and sum up to `1.0`.
+fn is_distribution(values: &[f64]) -> bool {
+    values.iter().all(|p| p.is_finite() && *p >= 0.0)
+        && (values.iter().sum::<f64>() - 1.0).abs() <= SUM_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::states::{StateDynamicProgram, StateDynamicProgramError};
+    use crate::dp::DynamicProgramPool;
+    use crate::kernel;
+    use crate::kernel::biased_rw::BiasedRwGenerator;
+    use crate::kernel::{Direction, Kernel};
+
+    fn resting_and_travelling(time_limit: usize) -> Vec<DynamicProgramPool> {
+        let state = |kernel: Kernel| {
+            DynamicProgramBuilder::new()
+                .simple()
+                .time_limit(time_limit)
+                .kernel(kernel)
+                .build()
+                .unwrap()
+        };
+
+        vec![
+            state(kernel!(0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0)),
+            state(
+                Kernel::from_generator(BiasedRwGenerator {
+                    probability: 1.0,
+                    direction: Direction::East,
+                })
+                .unwrap(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_state_dp() {
+        let mut dp = StateDynamicProgram::try_new(
+            resting_and_travelling(5),
+            vec![vec![0.5, 0.5], vec![0.25, 0.75]],
+            vec![1.0, 0.0],
+        )
+        .unwrap();
+
+        dp.compute();
+
+        assert_eq!(dp.at(0, 0, 1, 0), 0.5);
+        assert_eq!(dp.at(1, 0, 1, 1), 0.5);
+        assert_eq!(dp.at(0, 0, 1, 1), 0.0);
+        assert_eq!(dp.at(1, 0, 2, 0), 0.5 * 0.25);
+        assert_eq!(dp.at(1, 0, 2, 1), 0.5 * 0.5);
+        assert_eq!(dp.at(2, 0, 2, 1), 0.5 * 0.75);
+
+        for t in 0..=5 {
+            let mut sum = 0.0;
+
+            for x in 0..=5 {
+                sum += dp.marginal_at(x, 0, t);
+            }
+
+            assert!((sum - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_state_dp_invalid() {
+        let try_new = |switching, initial| {
+            StateDynamicProgram::try_new(resting_and_travelling(5), switching, initial)
+        };
+
+        assert_eq!(
+            try_new(vec![vec![0.5, 0.5], vec![0.5, 0.6]], vec![1.0, 0.0]).unwrap_err(),
+            StateDynamicProgramError::InvalidSwitchingMatrix
+        );
+        assert_eq!(
+            try_new(vec![vec![1.0], vec![1.0]], vec![1.0, 0.0]).unwrap_err(),
+            StateDynamicProgramError::InvalidSwitchingMatrix
+        );
+        assert_eq!(
+            try_new(vec![vec![0.5, 0.5], vec![0.5, 0.5]], vec![1.0]).unwrap_err(),
+            StateDynamicProgramError::InvalidInitialDistribution
+        );
+        assert_eq!(
+            StateDynamicProgram::try_new(Vec::new(), Vec::new(), Vec::new()).unwrap_err(),
+            StateDynamicProgramError::NoStates
+        );
+
+        let mut states = resting_and_travelling(5);
+        states.extend(resting_and_travelling(6).into_iter().take(1));
+
+        assert_eq!(
+            StateDynamicProgram::try_new(states, vec![vec![1.0 / 3.0; 3]; 3], vec![1.0, 0.0, 0.0])
+                .unwrap_err(),
+            StateDynamicProgramError::TimeLimitMismatch
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Provides habitat weights for step-selection dynamic programs.
+//!
+//! In step-selection models, the probability of a step from a source field `s` to a destination
+//! field `d` is the movement kernel multiplied by a habitat-selection weight `w(d)` of the
+//! destination, normalized over all destinations that can be reached from the source:
+//!
+//! ```text
+//! P(s -> d) = K(d - s) * w(d) / Σ_d' K(d' - s) * w(d')
+//! ```
+//!
+//! The kernel `K` is chosen by the dynamic program's
+//! [`TransitionModel`](crate::dp::transition::TransitionModel), which also applies crossing weights
+//! between field types if set. Destinations outside of the dynamic program's table are not part of
+//! the normalization, so no probability mass leaves the table.
+//!
+//! Habitat weights are set using
+//! [`DynamicProgramBuilder::habitat_weights()`](crate::dp::builder::DynamicProgramBuilder::habitat_weights)
+//! or computed from a stack of covariates `c_k` and coefficients `β_k` as `w = exp(Σ_k β_k * c_k)`
+//! using
+//! [`DynamicProgramBuilder::habitat_covariates()`](crate::dp::builder::DynamicProgramBuilder::habitat_covariates).
+//! Walks can be generated using the
+//! [`BacktrackingWalker`](crate::walker::backtracking::BacktrackingWalker).
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::dp::builder::DynamicProgramBuilder;
+//! use randomwalks_lib::dp::DynamicPrograms;
+//! use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
+//! use randomwalks_lib::kernel::Kernel;
+//!
+//! // A single covariate increasing to the east, which is preferred
+//! let covariate: Vec<Vec<f64>> = (0..21).map(|x| vec![x as f64 / 20.0; 21]).collect();
+//!
+//! let mut dp = DynamicProgramBuilder::new()
+//!     .simple()
+//!     .time_limit(10)
+//!     .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+//!     .habitat_covariates(vec![covariate], vec![2.0])
+//!     .build()
+//!     .unwrap();
+//!
+//! dp.compute();
+//!
+//! assert!(dp.at(3, 0, 10, 0).unwrap() > dp.at(-3, 0, 10, 0).unwrap());
+//! ```
+
+/// Computes habitat-selection weights `exp(Σ_k β_k * c_k)` from a stack of covariates `c_k` and
+/// coefficients `β_k`. Returns `None` if the number of covariates and coefficients differ or if
+/// the covariates are of different sizes.
+pub fn weights_from_covariates(
+    covariates: &[Vec<Vec<f64>>],
+    coefficients: &[f64],
+) -> Option<Vec<Vec<f64>>> {
+    let first = covariates.first()?;
+
+    if covariates.len() != coefficients.len()
+        || covariates.iter().any(|covariate| {
+            covariate.len() != first.len()
+                || covariate
+                    .iter()
+                    .zip(first)
+                    .any(|(column, first)| column.len() != first.len())
+        })
+    {
+        return None;
+    }
+
+    let mut weights = first.clone();
+
+    for (x, column) in weights.iter_mut().enumerate() {
+        for (y, weight) in column.iter_mut().enumerate() {
+            *weight = covariates
+                .iter()
+                .zip(coefficients)
+                .map(|(covariate, beta)| beta * covariate[x][y])
+                .sum::<f64>()
+                .exp();
+        }
+    }
+
+    Some(weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dp::builder::{DynamicProgramBuilder, DynamicProgramBuilderError};
+    use crate::dp::step_selection::weights_from_covariates;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::Kernel;
+
+    #[test]
+    fn test_step_selection_transitions() {
+        let mut weights = vec![vec![1.0; 11]; 11];
+        weights[6][5] = 3.0;
+
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(5)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .habitat_weights(weights)
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let DynamicProgramPool::Single(dp) = dp else {
+            unreachable!();
+        };
+
+        // The field east of the origin is three times as attractive as the others
+        assert!((dp.at(1, 0, 1) - 3.0 / 7.0).abs() < 1e-12);
+        assert!((dp.at(0, 0, 1) - 1.0 / 7.0).abs() < 1e-12);
+        assert!((dp.transition_probability(0, 0, 1, 0) - 3.0 / 7.0).abs() < 1e-12);
+
+        // Transitions leaving each field sum up to 1.0, even at the border of the table
+        for (x, y) in [(0, 0), (1, 0), (5, 5), (-5, 2)] {
+            let mut sum = 0.0;
+
+            for i in -1..=1 {
+                for j in -1..=1 {
+                    sum += dp.transition_probability(x, y, x + i, y + j);
+                }
+            }
+
+            assert!((sum - 1.0).abs() < 1e-12);
+        }
+
+        // No probability mass is lost
+        for t in 0..=5 {
+            assert!((dp.layer_statistics(t).mass - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_step_selection_covariates() {
+        let covariates = vec![vec![vec![1.0; 2]; 2], vec![vec![2.0; 2]; 2]];
+        let weights = weights_from_covariates(&covariates, &[0.5, -1.0]).unwrap();
+
+        assert!((weights[1][1] - (-1.5f64).exp()).abs() < 1e-12);
+        assert!(weights_from_covariates(&covariates, &[0.5]).is_none());
+        assert!(weights_from_covariates(&[], &[]).is_none());
+
+        let dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(5)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .habitat_covariates(covariates, vec![0.5, -1.0])
+            .build();
+
+        assert!(matches!(
+            dp,
+            Err(DynamicProgramBuilderError::WrongSizeOfHabitatWeights)
+        ));
+
+        let dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(1)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .habitat_weights(vec![vec![-1.0; 3]; 3])
+            .build();
+
+        assert!(matches!(
+            dp,
+            Err(DynamicProgramBuilderError::InvalidHabitatWeights)
+        ));
+    }
+}
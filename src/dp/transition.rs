@@ -0,0 +1,410 @@
+//! Provides the transition model shared by dynamic programs and walkers.
+//!
+//! The [`TransitionModel`] defines the probability `P(s -> d)` of a single step from a source
+//! field `s` to a destination field `d`. Dynamic programs use it to compute their tables and
+//! walkers use it, through
+//! [`DynamicProgram::transition_probability()`](crate::dp::simple::DynamicProgram::transition_probability),
+//! to sample paths backwards, so sampled paths always follow the same model as the dynamic
+//! program.
+//!
+//! The probability of a step is computed as
+//!
+//! ```text
+//! P(s -> d) = K(d - s) * c(type(s), type(d)) * w(d) / Z(s)
+//! ```
+//!
+//! where
+//!
+//! - `K` is the kernel of either the destination's or the source's field type, depending on the
+//! [`KernelSelection`]. By default, the kernel of the destination is used.
+//! - `c` is a crossing weight for steps from one field type into another, e.g. a cost for leaving
+//! a forest into a field or a lower probability of entering water. Crossing weights default to
+//! `1.0` and are set using
+//! [`DynamicProgramBuilder::crossing_weight()`](crate::dp::builder::DynamicProgramBuilder::crossing_weight).
+//! - `w` is the habitat-selection weight of the destination, see the
+//! [`step_selection`](crate::dp::step_selection) module. It defaults to `1.0`.
+//! - `Z(s)` normalizes all steps leaving `s` to sum up to `1.0`. Only destinations inside of the
+//! dynamic program's table are part of the normalization. Normalization is enabled using
+//! [`DynamicProgramBuilder::normalize_transitions()`](crate::dp::builder::DynamicProgramBuilder::normalize_transitions)
+//! and always used if habitat weights are set. Otherwise, `Z(s) = 1.0`, i.e. probability mass of
+//! steps that are made less likely is lost instead of being redistributed.
+//!
+//! Barriers can never be entered, independently of the kernel selection.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::dp::builder::DynamicProgramBuilder;
+//! use randomwalks_lib::dp::transition::KernelSelection;
+//! use randomwalks_lib::dp::{DynamicProgramPool, DynamicPrograms};
+//! use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
+//! use randomwalks_lib::kernel::Kernel;
+//!
+//! // Water (field type 1) in the east half
+//! let field_types: Vec<Vec<usize>> = (0..21)
+//!     .map(|x| vec![if x > 10 { 1 } else { 0 }; 21])
+//!     .collect();
+//! let kernel = Kernel::from_generator(SimpleRwGenerator).unwrap();
+//!
+//! let mut dp = DynamicProgramBuilder::new()
+//!     .simple()
+//!     .time_limit(10)
+//!     .kernels(vec![(0, kernel.clone()), (1, kernel)])
+//!     .field_types(field_types)
+//!     .kernel_selection(KernelSelection::Source)
+//!     .crossing_weight(0, 1, 0.1)
+//!     .normalize_transitions()
+//!     .build()
+//!     .unwrap();
+//!
+//! dp.compute();
+//!
+//! let DynamicProgramPool::Single(dp) = dp else { unreachable!() };
+//!
+//! assert!(dp.transition_probability(0, 0, 1, 0) < dp.transition_probability(0, 0, -1, 0));
+//! ```
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::kernel::Kernel;
+
+/// Determines which field type's kernel is used for a step.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KernelSelection {
+    /// The kernel of the field type of the destination is used.
+    #[default]
+    Destination,
+
+    /// The kernel of the field type of the source is used.
+    Source,
+}
+
+/// The probabilities of single steps used by a dynamic program and its walkers.
+///
+/// For details see the [`transition`](crate::dp::transition) module.
+///
+/// The model is serialized without the offsets of the kernels, which are restored when loading a
+/// dynamic program, see [`DynamicProgram::load()`](crate::dp::simple::DynamicProgram::load).
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransitionModel {
+    pub(crate) kernel_selection: KernelSelection,
+    #[serde(with = "crossing_weights")]
+    pub(crate) crossing_weights: HashMap<(usize, usize), f64>,
+    pub(crate) habitat_weights: Option<Vec<Vec<f64>>>,
+    pub(crate) barrier: Option<usize>,
+    pub(crate) normalizers: Option<Vec<Vec<f64>>>,
+    #[serde(skip)]
+    pub(crate) offsets: Vec<Vec<(isize, isize)>>,
+    #[serde(skip)]
+    pub(crate) all_offsets: Vec<(isize, isize)>,
+}
+
+impl TransitionModel {
+    /// Returns the [`KernelSelection`] of the model.
+    pub fn kernel_selection(&self) -> KernelSelection {
+        self.kernel_selection
+    }
+
+    /// Returns whether the transitions leaving each field are normalized.
+    pub fn is_normalized(&self) -> bool {
+        self.normalizers.is_some()
+    }
+
+    /// Returns the habitat-selection weight of the field at `(x, y)` or `None` if the model has
+    /// no habitat weights.
+    pub fn habitat_weight_at(&self, x: isize, y: isize) -> Option<f64> {
+        self.habitat_weights
+            .as_ref()
+            .map(|weights| *at(weights, x, y))
+    }
+
+    /// Returns the sum of the unnormalized probabilities of all steps leaving the field at
+    /// `(x, y)` or `None` if the transitions are not normalized.
+    pub fn normalizer_at(&self, x: isize, y: isize) -> Option<f64> {
+        self.normalizers
+            .as_ref()
+            .map(|normalizers| *at(normalizers, x, y))
+    }
+
+    /// Collects the offsets of the [support](Kernel::support) of all kernels, so that only
+    /// non-zero entries of sparse kernels are considered.
+    pub(crate) fn index(&mut self, kernels: &[Kernel]) {
+        self.offsets = kernels
+            .iter()
+            .map(|kernel| {
+                kernel
+                    .support()
+                    .into_iter()
+                    .map(|(x, y, _)| (x, y))
+                    .collect()
+            })
+            .collect();
+
+        let mut all_offsets: Vec<_> = self.offsets.iter().flatten().copied().collect();
+        all_offsets.sort_unstable();
+        all_offsets.dedup();
+
+        self.all_offsets = all_offsets;
+    }
+
+    /// Enables normalization and computes the normalizers for the given kernels and mapped field
+    /// types of a dynamic program.
+    pub(crate) fn normalize(&mut self, kernels: &[Kernel], field_types: &[Vec<usize>]) {
+        self.normalizers = None;
+
+        let limit = (field_types.len() / 2) as isize;
+        let mut normalizers = vec![vec![0.0; field_types.len()]; field_types.len()];
+
+        for x in -limit..=limit {
+            for y in -limit..=limit {
+                let mut sum = 0.0;
+
+                for &(dx, dy) in self.all_offsets.iter() {
+                    let (i, j) = (x + dx, y + dy);
+
+                    if i.abs() <= limit && j.abs() <= limit {
+                        sum += self.probability(kernels, field_types, (x, y), (i, j));
+                    }
+                }
+
+                normalizers[(limit + x) as usize][(limit + y) as usize] = sum;
+            }
+        }
+
+        self.normalizers = Some(normalizers);
+    }
+
+    /// Returns the probability of a step from `from` to `to`. Both fields must lie inside of the
+    /// dynamic program's table.
+    pub(crate) fn probability(
+        &self,
+        kernels: &[Kernel],
+        field_types: &[Vec<usize>],
+        from: (isize, isize),
+        to: (isize, isize),
+    ) -> f64 {
+        let from_type = *at(field_types, from.0, from.1);
+        let to_type = *at(field_types, to.0, to.1);
+
+        if self.barrier == Some(to_type) {
+            return 0.0;
+        }
+
+        let kernel = match self.kernel_selection {
+            KernelSelection::Destination => &kernels[to_type],
+            KernelSelection::Source => &kernels[from_type],
+        };
+        let mut p = kernel.at_or(to.0 - from.0, to.1 - from.1, 0.0);
+
+        if p == 0.0 {
+            return 0.0;
+        }
+
+        if !self.crossing_weights.is_empty() {
+            p *= self
+                .crossing_weights
+                .get(&(from_type, to_type))
+                .unwrap_or(&1.0);
+        }
+
+        if let Some(weights) = &self.habitat_weights {
+            p *= at(weights, to.0, to.1);
+        }
+
+        if let Some(normalizers) = &self.normalizers {
+            let normalizer = at(normalizers, from.0, from.1);
+
+            p = if *normalizer == 0.0 {
+                0.0
+            } else {
+                p / normalizer
+            };
+        }
+
+        p
+    }
+
+    /// Returns the offsets `to - from` of all fields `from` from which `to` can possibly be reached
+    /// in a single step. The kernels must have been [indexed](TransitionModel::index).
+    pub(crate) fn offsets(
+        &self,
+        field_types: &[Vec<usize>],
+        to: (isize, isize),
+    ) -> &[(isize, isize)] {
+        match self.kernel_selection {
+            KernelSelection::Destination => &self.offsets[*at(field_types, to.0, to.1)],
+            KernelSelection::Source => &self.all_offsets,
+        }
+    }
+}
+
+/// Serializes crossing weights as a list of `((from, to), weight)`, since JSON only supports
+/// strings as keys.
+mod crossing_weights {
+    use super::*;
+
+    pub(super) fn serialize<S: Serializer>(
+        weights: &HashMap<(usize, usize), f64>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut weights: Vec<_> = weights.iter().collect();
+        weights.sort_by_key(|(types, _)| **types);
+
+        weights.serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<(usize, usize), f64>, D::Error> {
+        Ok(Vec::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+/// Returns the largest radius of all kernels.
+pub(crate) fn max_radius(kernels: &[Kernel]) -> isize {
+    kernels
+        .iter()
+        .map(|kernel| kernel.size() / 2)
+        .max()
+        .unwrap_or(0) as isize
+}
+
+/// Returns the entry at `(x, y)` of a grid with the origin in its center.
+fn at<T>(grid: &[Vec<T>], x: isize, y: isize) -> &T {
+    let limit = (grid.len() / 2) as isize;
+
+    &grid[(limit + x) as usize][(limit + y) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::point::XYPoint;
+    use crate::dp::builder::{DynamicProgramBuilder, DynamicProgramBuilderError};
+    use crate::dp::transition::KernelSelection;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::biased_rw::BiasedRwGenerator;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::{Direction, Kernel};
+    use crate::xy;
+
+    #[test]
+    fn test_kernel_selection() {
+        // A field of type 1 east of the origin whose kernel only moves east
+        let mut field_types = vec![vec![0; 11]; 11];
+        field_types[6][5] = 1;
+
+        let east = Kernel::from_generator(BiasedRwGenerator {
+            probability: 1.0,
+            direction: Direction::East,
+        })
+        .unwrap();
+        let kernels = vec![
+            (0, Kernel::from_generator(SimpleRwGenerator).unwrap()),
+            (1, east),
+        ];
+
+        for (selection, expected) in [
+            (KernelSelection::Destination, [1.0, 0.2]),
+            (KernelSelection::Source, [0.2, 0.0]),
+        ] {
+            let mut dp = DynamicProgramBuilder::new()
+                .simple()
+                .time_limit(5)
+                .kernels(kernels.clone())
+                .field_types(field_types.clone())
+                .kernel_selection(selection)
+                .build()
+                .unwrap();
+
+            dp.compute();
+
+            let DynamicProgramPool::Single(dp) = dp else {
+                unreachable!();
+            };
+
+            // Entering (1, 0) from the origin and leaving it to the north
+            assert!((dp.transition_probability(0, 0, 1, 0) - expected[0]).abs() < 1e-12);
+            assert!((dp.transition_probability(1, 0, 1, -1) - expected[1]).abs() < 1e-12);
+            assert!((dp.at(1, 0, 1) - expected[0]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_crossing_weights() {
+        // Water (field type 1) east of x = 0
+        let field_types: Vec<Vec<usize>> = (0..11)
+            .map(|x| vec![if x > 5 { 1 } else { 0 }; 11])
+            .collect();
+        let kernel = Kernel::from_generator(SimpleRwGenerator).unwrap();
+        let builder = || {
+            DynamicProgramBuilder::new()
+                .simple()
+                .time_limit(5)
+                .kernels(vec![(0, kernel.clone()), (1, kernel.clone())])
+                .field_types(field_types.clone())
+                .crossing_weight(0, 1, 0.5)
+        };
+
+        let mut dp = builder().build().unwrap();
+        dp.compute();
+
+        let DynamicProgramPool::Single(dp) = dp else {
+            unreachable!();
+        };
+
+        assert!((dp.transition_probability(0, 0, 1, 0) - 0.1).abs() < 1e-12);
+        assert!((dp.transition_probability(1, 0, 2, 0) - 0.2).abs() < 1e-12);
+        assert!((dp.transition_probability(1, 0, 0, 0) - 0.2).abs() < 1e-12);
+        assert!((dp.layer_statistics(1).mass - 0.9).abs() < 1e-12);
+
+        let mut dp = builder().normalize_transitions().build().unwrap();
+        dp.compute();
+
+        let DynamicProgramPool::Single(dp) = dp else {
+            unreachable!();
+        };
+
+        assert!((dp.transition_probability(0, 0, 1, 0) - 0.1 / 0.9).abs() < 1e-12);
+        assert!((dp.transition_probability(0, 0, -1, 0) - 0.2 / 0.9).abs() < 1e-12);
+
+        for t in 0..=5 {
+            assert!((dp.layer_statistics(t).mass - 1.0).abs() < 1e-12);
+        }
+
+        assert!(matches!(
+            builder().crossing_weight(0, 2, 0.5).build(),
+            Err(DynamicProgramBuilderError::UnknownCrossingFieldType(2))
+        ));
+        assert!(matches!(
+            builder().crossing_weight(1, 0, -1.0).build(),
+            Err(DynamicProgramBuilderError::InvalidCrossingWeight)
+        ));
+    }
+
+    #[test]
+    fn test_source_selection_barrier() {
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(5)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .kernel_selection(KernelSelection::Source)
+            .add_rect_barrier(xy!(1, -5), xy!(1, 5))
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let DynamicProgramPool::Single(dp) = dp else {
+            unreachable!();
+        };
+
+        for t in 0..=5 {
+            for y in -5..=5 {
+                assert_eq!(dp.at(1, y, t), 0.0);
+                assert_eq!(dp.at(2, y, t), 0.0);
+            }
+        }
+    }
+}
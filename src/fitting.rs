@@ -0,0 +1,404 @@
+//! Provides maximum-likelihood fitting of kernel generator parameters from observed walks.
+//!
+//! All fitting functions take a set of gridded tracks in the form of [`Walk`]s in which
+//! consecutive points are exactly one time step apart. Tracks stored in a
+//! [`Dataset`](crate::dataset::Dataset) with XY coordinates can be converted using
+//! [`Dataset::to_walks()`](crate::dataset::Dataset::to_walks).
+//!
+//! The parameters of the kernel generators are estimated by maximizing the log-likelihood of all
+//! steps of the walks, see the [`likelihood`](crate::likelihood) module. Every fitting function
+//! returns a [`FitResult`] containing the fitted generator together with its log-likelihood,
+//! information criteria and the standard errors of the estimated parameters. Since AIC and BIC
+//! are comparable between different models fitted on the same walks, they can be used to choose
+//! the walk model that describes the walks best.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::dataset::point::XYPoint;
+//! use randomwalks_lib::fitting::{fit_biased_rw, fit_simple_rw};
+//! use randomwalks_lib::walk::Walk;
+//! use randomwalks_lib::xy;
+//!
+//! let walks = vec![Walk(vec![xy!(0, 0), xy!(1, 0), xy!(2, 0), xy!(2, 1), xy!(3, 1)])];
+//!
+//! let simple = fit_simple_rw(&walks).unwrap();
+//! let biased = fit_biased_rw(&walks).unwrap();
+//!
+//! assert!(biased.aic < simple.aic);
+//! ```
+
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+use crate::dataset::point::XYPoint;
+use crate::kernel::biased_rw::BiasedRwGenerator;
+use crate::kernel::correlated_rw::CorrelatedRwGenerator;
+use crate::kernel::generator::KernelGeneratorError;
+use crate::kernel::normal_dist::NormalDistGenerator;
+use crate::kernel::simple_rw::SimpleRwGenerator;
+use crate::kernel::{Direction, Kernel};
+use crate::likelihood::{LikelihoodError, MovementModel};
+use crate::walk::Walk;
+
+/// The number of iterations of the golden-section search used for optimization.
+const ITERATIONS: usize = 100;
+
+/// An error that can occur when fitting a kernel generator.
+#[derive(Error, Debug)]
+pub enum FittingError {
+    /// This error occurs when the given walks do not contain a single step.
+    #[error("the walks must contain at least one step")]
+    NoSteps,
+
+    /// This error occurs when a step of the walks is impossible for every parameter value of
+    /// the model, e.g. because it is longer than the kernel size.
+    #[error("the walks cannot be described by the model: {0}")]
+    Likelihood(#[from] LikelihoodError),
+
+    /// This error occurs when the kernel generator fails to generate a kernel.
+    #[error("could not generate kernel: {0}")]
+    KernelGenerator(#[from] KernelGeneratorError),
+}
+
+/// The result of fitting a kernel generator.
+#[derive(Debug)]
+pub struct FitResult<G> {
+    /// The kernel generator using the fitted parameters.
+    pub generator: G,
+
+    /// The names and values of the fitted parameters.
+    pub parameters: Vec<(&'static str, f64)>,
+
+    /// The standard errors of the fitted parameters in the same order as `parameters`. They are
+    /// computed from the observed Fisher information and are `NaN` if the estimate lies on the
+    /// boundary of the parameter space.
+    pub standard_errors: Vec<f64>,
+
+    /// The maximized log-likelihood.
+    pub log_likelihood: f64,
+
+    /// The number of steps the model was fitted on.
+    pub observations: usize,
+
+    /// The number of free parameters of the model.
+    pub free_parameters: usize,
+
+    /// Akaike information criterion, `2k - 2 ln(L)`.
+    pub aic: f64,
+
+    /// Bayesian information criterion, `k ln(n) - 2 ln(L)`.
+    pub bic: f64,
+}
+
+impl<G> FitResult<G> {
+    fn new(
+        generator: G,
+        parameters: Vec<(&'static str, f64)>,
+        standard_errors: Vec<f64>,
+        log_likelihood: f64,
+        observations: usize,
+        free_parameters: usize,
+    ) -> Self {
+        let k = free_parameters as f64;
+
+        Self {
+            generator,
+            parameters,
+            standard_errors,
+            log_likelihood,
+            observations,
+            free_parameters,
+            aic: 2.0 * k - 2.0 * log_likelihood,
+            bic: k * (observations as f64).ln() - 2.0 * log_likelihood,
+        }
+    }
+}
+
+/// Computes the log-likelihood of the simple random walk, which has no free parameters. This
+/// serves as a baseline for comparison with the other models.
+pub fn fit_simple_rw(walks: &[Walk]) -> Result<FitResult<SimpleRwGenerator>, FittingError> {
+    let observations = count_steps(walks)?;
+    let model = MovementModel::Kernel(Kernel::from_generator(SimpleRwGenerator)?);
+    let log_likelihood = log_likelihood(walks, &model)?;
+
+    Ok(FitResult::new(
+        SimpleRwGenerator,
+        Vec::new(),
+        Vec::new(),
+        log_likelihood,
+        observations,
+        0,
+    ))
+}
+
+/// Fits the `probability` and `direction` of a [`BiasedRwGenerator`].
+pub fn fit_biased_rw(walks: &[Walk]) -> Result<FitResult<BiasedRwGenerator>, FittingError> {
+    let observations = count_steps(walks)?;
+    let mut best: Option<(Direction, f64, f64)> = None;
+
+    for direction in Direction::iter() {
+        let model = |probability: f64| {
+            Ok(MovementModel::Kernel(Kernel::from_generator(
+                BiasedRwGenerator {
+                    probability,
+                    direction,
+                },
+            )?))
+        };
+
+        let probability = maximize(walks, model, 0.0, 1.0)?;
+        let log_likelihood =
+            log_likelihood(walks, &model(probability)?).unwrap_or(f64::NEG_INFINITY);
+
+        if best.map_or(true, |(_, _, best_ll)| log_likelihood > best_ll) {
+            best = Some((direction, probability, log_likelihood));
+        }
+    }
+
+    let (direction, probability, _) = best.unwrap();
+    let model = |probability: f64| {
+        Ok(MovementModel::Kernel(Kernel::from_generator(
+            BiasedRwGenerator {
+                probability,
+                direction,
+            },
+        )?))
+    };
+    let log_likelihood = log_likelihood(walks, &model(probability)?)?;
+    let standard_error = standard_error(walks, model, probability, 0.0, 1.0)?;
+
+    Ok(FitResult::new(
+        BiasedRwGenerator {
+            probability,
+            direction,
+        },
+        vec![("probability", probability)],
+        vec![standard_error],
+        log_likelihood,
+        observations,
+        2,
+    ))
+}
+
+/// Fits the `persistence` of a [`CorrelatedRwGenerator`].
+///
+/// The walks are evaluated using a [`MovementModel::Correlated`] model, i.e. the kernel for each
+/// step is chosen by the direction of the previous step.
+pub fn fit_correlated_rw(walks: &[Walk]) -> Result<FitResult<CorrelatedRwGenerator>, FittingError> {
+    let observations = count_steps(walks)?;
+    let model = |persistence: f64| {
+        Ok(MovementModel::Correlated(Kernel::multiple_from_generator(
+            CorrelatedRwGenerator { persistence },
+        )?))
+    };
+
+    let persistence = maximize(walks, model, 0.0, 1.0)?;
+    let log_likelihood = log_likelihood(walks, &model(persistence)?)?;
+    let standard_error = standard_error(walks, model, persistence, 0.0, 1.0)?;
+
+    Ok(FitResult::new(
+        CorrelatedRwGenerator { persistence },
+        vec![("persistence", persistence)],
+        vec![standard_error],
+        log_likelihood,
+        observations,
+        1,
+    ))
+}
+
+/// Fits the `diffusion` of a [`NormalDistGenerator`] with the given kernel `size` and a mean of
+/// `(0, 0)`.
+pub fn fit_normal_dist(
+    walks: &[Walk],
+    size: usize,
+) -> Result<FitResult<NormalDistGenerator>, FittingError> {
+    let observations = count_steps(walks)?;
+    let model = |diffusion: f64| {
+        Ok(MovementModel::Kernel(Kernel::from_generator(
+            NormalDistGenerator::new(diffusion, size, XYPoint::default()),
+        )?))
+    };
+
+    // The diffusion is optimized on a log scale, since the likelihood changes much faster for
+    // small values
+    let (lower, upper) = (1e-3f64.ln(), ((size * size) as f64).ln());
+    let log_model = |log_diffusion: f64| model(log_diffusion.exp());
+
+    let diffusion = maximize(walks, log_model, lower, upper)?.exp();
+    let log_likelihood = log_likelihood(walks, &model(diffusion)?)?;
+    let standard_error = standard_error(walks, model, diffusion, 0.0, f64::INFINITY)?;
+
+    Ok(FitResult::new(
+        NormalDistGenerator::new(diffusion, size, XYPoint::default()),
+        vec![("diffusion", diffusion)],
+        vec![standard_error],
+        log_likelihood,
+        observations,
+        1,
+    ))
+}
+
+/// Counts the steps of all walks.
+fn count_steps(walks: &[Walk]) -> Result<usize, FittingError> {
+    let steps = walks
+        .iter()
+        .map(|walk| walk.len().saturating_sub(1))
+        .sum::<usize>();
+
+    if steps == 0 {
+        Err(FittingError::NoSteps)
+    } else {
+        Ok(steps)
+    }
+}
+
+/// Computes the sum of the log-likelihoods of all walks. Walks without any steps are skipped.
+fn log_likelihood(walks: &[Walk], model: &MovementModel) -> Result<f64, LikelihoodError> {
+    let mut sum = 0.0;
+
+    for walk in walks.iter().filter(|walk| walk.len() >= 2) {
+        sum += model.log_likelihood(walk)?.log_likelihood;
+    }
+
+    Ok(sum)
+}
+
+/// Maximizes the log-likelihood of the model in the parameter range `[lower, upper]` using a
+/// golden-section search. Impossible parameter values are treated as having a log-likelihood
+/// of negative infinity.
+fn maximize<F>(walks: &[Walk], model: F, lower: f64, upper: f64) -> Result<f64, FittingError>
+where
+    F: Fn(f64) -> Result<MovementModel, FittingError>,
+{
+    let objective = |parameter: f64| -> Result<f64, FittingError> {
+        Ok(log_likelihood(walks, &model(parameter)?).unwrap_or(f64::NEG_INFINITY))
+    };
+
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut a, mut b) = (lower, upper);
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    let (mut f_c, mut f_d) = (objective(c)?, objective(d)?);
+
+    for _ in 0..ITERATIONS {
+        if f_c >= f_d {
+            b = d;
+            d = c;
+            f_d = f_c;
+            c = b - ratio * (b - a);
+            f_c = objective(c)?;
+        } else {
+            a = c;
+            c = d;
+            f_c = f_d;
+            d = a + ratio * (b - a);
+            f_d = objective(d)?;
+        }
+    }
+
+    let parameter = (a + b) / 2.0;
+
+    // Make sure that a model which cannot describe the walks at all is reported as an error
+    log_likelihood(walks, &model(parameter)?)?;
+
+    Ok(parameter)
+}
+
+/// Computes the standard error of an estimate using a numerical approximation of the second
+/// derivative of the log-likelihood.
+fn standard_error<F>(
+    walks: &[Walk],
+    model: F,
+    estimate: f64,
+    lower: f64,
+    upper: f64,
+) -> Result<f64, FittingError>
+where
+    F: Fn(f64) -> Result<MovementModel, FittingError>,
+{
+    let h = 1e-4 * estimate.abs().max(1e-2);
+
+    if estimate - h <= lower || estimate + h >= upper {
+        return Ok(f64::NAN);
+    }
+
+    let objective = |parameter: f64| -> Result<f64, FittingError> {
+        Ok(log_likelihood(walks, &model(parameter)?)?)
+    };
+
+    let second_derivative = (objective(estimate + h)? - 2.0 * objective(estimate)?
+        + objective(estimate - h)?)
+        / (h * h);
+
+    if second_derivative < 0.0 {
+        Ok((-1.0 / second_derivative).sqrt())
+    } else {
+        Ok(f64::NAN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::point::XYPoint;
+    use crate::fitting::{fit_biased_rw, fit_correlated_rw, fit_normal_dist, FittingError};
+    use crate::kernel::Direction;
+    use crate::walk::Walk;
+    use crate::xy;
+
+    #[test]
+    fn test_fit_biased_rw() {
+        // 6 of 10 steps go north, the remaining steps are equally distributed
+        let walk = Walk(vec![
+            xy!(0, 0),
+            xy!(0, -1),
+            xy!(0, -2),
+            xy!(0, -3),
+            xy!(1, -3),
+            xy!(1, -4),
+            xy!(1, -4),
+            xy!(1, -5),
+            xy!(0, -5),
+            xy!(0, -6),
+            xy!(0, -5),
+        ]);
+
+        let fit = fit_biased_rw(&[walk]).unwrap();
+
+        assert_eq!(fit.generator.direction, Direction::North);
+        assert!((fit.generator.probability - 0.6).abs() < 1e-6);
+        // Analytical standard error of a binomial proportion
+        assert!((fit.standard_errors[0] - (0.6f64 * 0.4 / 10.0).sqrt()).abs() < 1e-3);
+        assert_eq!(fit.observations, 10);
+    }
+
+    #[test]
+    fn test_fit_correlated_rw() {
+        let straight = Walk((0..20).map(|i| xy!(i, 0)).collect());
+        let fit = fit_correlated_rw(&[straight]).unwrap();
+
+        assert!(fit.generator.persistence > 0.99);
+    }
+
+    #[test]
+    fn test_fit_normal_dist() {
+        let still = Walk(vec![xy!(0, 0); 10]);
+        let moving = Walk(vec![xy!(0, 0), xy!(2, 0), xy!(2, 2), xy!(0, 2), xy!(0, 0)]);
+
+        let fit_still = fit_normal_dist(&[still], 5).unwrap();
+        let fit_moving = fit_normal_dist(&[moving], 5).unwrap();
+
+        assert!(fit_still.generator.diffusion < fit_moving.generator.diffusion);
+    }
+
+    #[test]
+    fn test_fit_impossible() {
+        let walk = Walk(vec![xy!(0, 0), xy!(5, 0)]);
+
+        assert!(matches!(
+            fit_normal_dist(&[walk], 3),
+            Err(FittingError::Likelihood(_))
+        ));
+        assert!(matches!(fit_biased_rw(&[]), Err(FittingError::NoSteps)));
+    }
+}
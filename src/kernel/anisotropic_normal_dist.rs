@@ -0,0 +1,264 @@
+//! Provides a generator for kernels following an anisotropic two-dimensional normal distribution.
+//!
+//! Unlike the [`NormalDistGenerator`](crate::kernel::normal_dist::NormalDistGenerator), the
+//! covariance of the distribution does not have to be diagonal. It is given by an orientation
+//! `angle` in degrees and two principal `variances`. The first variance is the variance along the
+//! axis obtained by rotating the x-axis by `angle` towards the positive y-axis, the second
+//! variance is the one along the perpendicular axis. Alternatively, a full covariance matrix can
+//! be given using [`from_covariance()`](AnisotropicNormalDistGenerator::from_covariance).
+//!
+//! The mean of the distribution is shifted from the center of the kernel by a real-valued `drift`,
+//! which does not have to lie on the grid.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::kernel::anisotropic_normal_dist::AnisotropicNormalDistGenerator;
+//! use randomwalks_lib::kernel::Kernel;
+//!
+//! // Elongated along the diagonal and drifting to the west
+//! let kernel = Kernel::from_generator(AnisotropicNormalDistGenerator {
+//!     angle: 45.0,
+//!     variances: (4.0, 0.5),
+//!     drift: (-1.5, 0.0),
+//!     size: 11,
+//! })
+//! .unwrap();
+//! ```
+
+use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
+use crate::kernel::Kernel;
+
+pub struct AnisotropicNormalDistGenerator {
+    pub angle: f64,
+    pub variances: (f64, f64),
+    pub drift: (f64, f64),
+    pub size: usize,
+}
+
+impl AnisotropicNormalDistGenerator {
+    /// Creates a generator from a full 2x2 covariance matrix `[[xx, xy], [yx, yy]]`.
+    ///
+    /// Returns an error if the matrix is not symmetric and positive-definite.
+    pub fn from_covariance(
+        covariance: [[f64; 2]; 2],
+        drift: (f64, f64),
+        size: usize,
+    ) -> Result<Self, KernelGeneratorError> {
+        let [[xx, xy], [yx, yy]] = covariance;
+
+        if covariance.iter().flatten().any(|v| !v.is_finite()) {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "covariance must be finite".into(),
+            ));
+        }
+
+        if (xy - yx).abs() > 1e-12 * xy.abs().max(yx.abs()).max(1.0) {
+            return Err(KernelGeneratorError::CovarianceNotSymmetric);
+        }
+
+        // Eigenvalues of the symmetric matrix
+        let mean = (xx + yy) / 2.0;
+        let radius = (((xx - yy) / 2.0).powi(2) + xy * xy).sqrt();
+        let variances = (mean + radius, mean - radius);
+
+        if variances.1 <= 0.0 {
+            return Err(KernelGeneratorError::CovarianceNotPositiveDefinite);
+        }
+
+        // Orientation of the eigenvector of the larger eigenvalue
+        let angle = (2.0 * xy).atan2(xx - yy).to_degrees() / 2.0;
+
+        Ok(Self {
+            angle,
+            variances,
+            drift,
+            size,
+        })
+    }
+
+    /// Returns the covariance matrix `[[xx, xy], [yx, yy]]` of the distribution.
+    pub fn covariance(&self) -> [[f64; 2]; 2] {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let (v1, v2) = self.variances;
+
+        let xx = v1 * cos * cos + v2 * sin * sin;
+        let yy = v1 * sin * sin + v2 * cos * cos;
+        let xy = (v1 - v2) * sin * cos;
+
+        [[xx, xy], [xy, yy]]
+    }
+}
+
+impl KernelGenerator for AnisotropicNormalDistGenerator {
+    fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        if !self.angle.is_finite() || !self.drift.0.is_finite() || !self.drift.1.is_finite() {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "angle and drift must be finite".into(),
+            ));
+        }
+
+        let (v1, v2) = self.variances;
+
+        if !(v1.is_finite() && v2.is_finite() && v1 > 0.0 && v2 > 0.0) {
+            return Err(KernelGeneratorError::CovarianceNotPositiveDefinite);
+        }
+
+        kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?
+            .initialize(self.size)?;
+
+        Ok(())
+    }
+
+    fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        let kernel = kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?;
+
+        let [[xx, xy], [_, yy]] = self.covariance();
+        let det = xx * yy - xy * xy;
+        let radius = (self.size / 2) as isize;
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                let dx = x as f64 - self.drift.0;
+                let dy = y as f64 - self.drift.1;
+
+                // Quadratic form with the inverse covariance matrix
+                let q = (yy * dx * dx - 2.0 * xy * dx * dy + xx * dy * dy) / det;
+
+                kernel.set(x, y, (-0.5 * q).exp());
+            }
+        }
+
+        // Normalize values so that they sum up to 1.0
+        let sum = kernel.sum();
+
+        if sum == 0.0 || !sum.is_finite() {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "distribution has no mass inside of the kernel".into(),
+            ));
+        }
+
+        for x in 0..self.size {
+            for y in 0..self.size {
+                kernel.probabilities[x][y] /= sum;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generates_qty(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> (String, String) {
+        ("and".into(), "Anisotropic Normal Distribution".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::anisotropic_normal_dist::AnisotropicNormalDistGenerator;
+    use crate::kernel::generator::KernelGeneratorError;
+    use crate::kernel::Kernel;
+
+    /// Returns the mean and covariance of the kernel.
+    fn moments(kernel: &Kernel) -> ((f64, f64), [[f64; 2]; 2]) {
+        let radius = (kernel.size() / 2) as isize;
+        let (mut mx, mut my) = (0.0, 0.0);
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                mx += x as f64 * kernel.at(x, y);
+                my += y as f64 * kernel.at(x, y);
+            }
+        }
+
+        let mut cov = [[0.0; 2]; 2];
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                let (dx, dy) = (x as f64 - mx, y as f64 - my);
+
+                cov[0][0] += dx * dx * kernel.at(x, y);
+                cov[0][1] += dx * dy * kernel.at(x, y);
+                cov[1][1] += dy * dy * kernel.at(x, y);
+            }
+        }
+
+        cov[1][0] = cov[0][1];
+
+        ((mx, my), cov)
+    }
+
+    #[test]
+    fn test_anisotropic_normal_dist() {
+        let generator = AnisotropicNormalDistGenerator {
+            angle: 30.0,
+            variances: (3.0, 1.0),
+            drift: (-1.25, 0.5),
+            size: 31,
+        };
+        let expected = generator.covariance();
+        let kernel = Kernel::from_generator(generator).unwrap();
+        let ((mx, my), cov) = moments(&kernel);
+
+        assert!((kernel.sum() - 1.0).abs() < 1e-12);
+        assert!((mx + 1.25).abs() < 1e-6);
+        assert!((my - 0.5).abs() < 1e-6);
+
+        for i in 0..2 {
+            for j in 0..2 {
+                assert!((cov[i][j] - expected[i][j]).abs() < 1e-6);
+            }
+        }
+
+        // Elongated towards the south-east
+        assert!(kernel.at(2, 2) > kernel.at(2, -2));
+    }
+
+    #[test]
+    fn test_from_covariance() {
+        let generator = AnisotropicNormalDistGenerator::from_covariance(
+            [[2.0, 0.5], [0.5, 1.0]],
+            (0.0, 0.0),
+            5,
+        )
+        .unwrap();
+        let covariance = generator.covariance();
+
+        assert!((covariance[0][0] - 2.0).abs() < 1e-12);
+        assert!((covariance[0][1] - 0.5).abs() < 1e-12);
+        assert!((covariance[1][1] - 1.0).abs() < 1e-12);
+
+        assert!(matches!(
+            AnisotropicNormalDistGenerator::from_covariance(
+                [[1.0, 2.0], [2.0, 1.0]],
+                (0.0, 0.0),
+                5
+            ),
+            Err(KernelGeneratorError::CovarianceNotPositiveDefinite)
+        ));
+        assert!(matches!(
+            AnisotropicNormalDistGenerator::from_covariance(
+                [[1.0, 0.2], [0.3, 1.0]],
+                (0.0, 0.0),
+                5
+            ),
+            Err(KernelGeneratorError::CovarianceNotSymmetric)
+        ));
+        assert!(matches!(
+            Kernel::from_generator(AnisotropicNormalDistGenerator {
+                angle: 0.0,
+                variances: (1.0, 0.0),
+                drift: (0.0, 0.0),
+                size: 5,
+            }),
+            Err(KernelGeneratorError::CovarianceNotPositiveDefinite)
+        ));
+    }
+}
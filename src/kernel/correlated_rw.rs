@@ -42,7 +42,7 @@ impl KernelGenerator for CorrelatedRwGenerator {
     }
 
     fn name(&self) -> (String, String) {
-        ("brw".into(), "Biased RW".into())
+        ("crw".into(), "Correlated RW".into())
     }
 }
 
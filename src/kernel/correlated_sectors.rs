@@ -0,0 +1,243 @@
+//! Provides correlated kernel families for an arbitrary number of heading sectors.
+//!
+//! The full circle of headings is divided into `n` equally sized [`HeadingSectors`]. Sector `0` is
+//! centered on north (the negative y direction) and the following sectors continue clockwise.
+//! Staying in place has no heading and is mapped to the additional variant `n`. With four sectors,
+//! the variants are therefore ordered like `Direction::iter()`, i.e. north, east, south, west and
+//! stay, which is the same order as used by the
+//! [`CorrelatedRwGenerator`](crate::kernel::correlated_rw::CorrelatedRwGenerator).
+//!
+//! The [`CorrelatedSectorsGenerator`] generates one kernel per variant. The kernel of each sector
+//! is a [`DirectionalGenerator`] kernel heading towards the center of the sector, while the kernel
+//! of the stay variant uses uniformly distributed headings. Dynamic programs computed with these
+//! kernels and walkers such as the
+//! [`CorrelatedMultiStepWalker`](crate::walker::correlated_multi_step::CorrelatedMultiStepWalker)
+//! use [`HeadingSectors::variant()`] to agree on the variant belonging to each movement.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::kernel::correlated_sectors::{CorrelatedSectorsGenerator, HeadingSectors};
+//! use randomwalks_lib::kernel::directional::{HeadingDistribution, StepLengthDistribution};
+//! use randomwalks_lib::kernel::Kernel;
+//!
+//! let sectors = HeadingSectors::new(8);
+//! let kernels = Kernel::multiple_from_generator(CorrelatedSectorsGenerator {
+//!     sectors,
+//!     heading_distribution: HeadingDistribution::VonMises { concentration: 2.0 },
+//!     step_length: StepLengthDistribution::Gamma {
+//!         shape: 2.0,
+//!         scale: 1.0,
+//!     },
+//!     size: 7,
+//! })
+//! .unwrap();
+//!
+//! assert_eq!(kernels.len(), 9);
+//! assert_eq!(sectors.variant(1, -1), 1); // north-east
+//! assert_eq!(sectors.variant(0, 0), 8); // stay
+//! ```
+
+use std::f64::consts::TAU;
+
+use crate::kernel::directional::{
+    DirectionalGenerator, HeadingDistribution, StepLengthDistribution,
+};
+use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
+use crate::kernel::Kernel;
+
+/// A division of all headings into equally sized sectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadingSectors {
+    sectors: usize,
+}
+
+impl HeadingSectors {
+    /// Creates `sectors` heading sectors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sectors` is zero.
+    pub fn new(sectors: usize) -> Self {
+        assert!(sectors > 0, "at least one heading sector is required");
+
+        Self { sectors }
+    }
+
+    /// Returns the number of heading sectors.
+    pub fn sectors(&self) -> usize {
+        self.sectors
+    }
+
+    /// Returns the number of variants, i.e. the number of sectors plus one variant for staying.
+    pub fn variants(&self) -> usize {
+        self.sectors + 1
+    }
+
+    /// Returns the variant of a movement by `(dx, dy)`.
+    pub fn variant(&self, dx: isize, dy: isize) -> usize {
+        self.variant_of(dx as f64, dy as f64)
+    }
+
+    /// Returns the variant of a heading given as a vector `(dx, dy)` with arbitrary length.
+    pub fn variant_of(&self, dx: f64, dy: f64) -> usize {
+        if dx == 0.0 && dy == 0.0 {
+            return self.sectors;
+        }
+
+        let width = TAU / self.sectors as f64;
+        let bearing = dx.atan2(-dy).rem_euclid(TAU);
+
+        ((bearing + width / 2.0) / width).floor() as usize % self.sectors
+    }
+
+    /// Returns the heading at the center of `sector` as a bearing in degrees.
+    pub fn heading(&self, sector: usize) -> f64 {
+        360.0 * sector as f64 / self.sectors as f64
+    }
+}
+
+pub struct CorrelatedSectorsGenerator {
+    pub sectors: HeadingSectors,
+    pub heading_distribution: HeadingDistribution,
+    pub step_length: StepLengthDistribution,
+    pub size: usize,
+}
+
+impl KernelGenerator for CorrelatedSectorsGenerator {
+    fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        if kernels.len() != self.generates_qty() {
+            return Err(KernelGeneratorError::NotEnoughKernels);
+        }
+
+        for kernel in kernels.iter_mut() {
+            kernel.initialize(self.size)?;
+        }
+
+        Ok(())
+    }
+
+    fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        if kernels.len() != self.generates_qty() {
+            return Err(KernelGeneratorError::NotEnoughKernels);
+        }
+
+        for sector in 0..self.sectors.sectors() {
+            kernels[sector] = Kernel::from_generator(DirectionalGenerator {
+                heading: self.sectors.heading(sector),
+                heading_distribution: self.heading_distribution,
+                step_length: self.step_length,
+                size: self.size,
+            })?;
+        }
+
+        // Staying has no heading, so all headings are equally likely afterwards
+        let uniform = match self.heading_distribution {
+            HeadingDistribution::VonMises { .. } => {
+                HeadingDistribution::VonMises { concentration: 0.0 }
+            }
+            HeadingDistribution::WrappedCauchy { .. } => {
+                HeadingDistribution::WrappedCauchy { concentration: 0.0 }
+            }
+        };
+
+        kernels[self.sectors.sectors()] = Kernel::from_generator(DirectionalGenerator {
+            heading: 0.0,
+            heading_distribution: uniform,
+            step_length: self.step_length,
+            size: self.size,
+        })?;
+
+        Ok(())
+    }
+
+    fn generates_qty(&self) -> usize {
+        self.sectors.variants()
+    }
+
+    fn name(&self) -> (String, String) {
+        ("csrw".into(), "Correlated Sectors RW".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::correlated_sectors::{CorrelatedSectorsGenerator, HeadingSectors};
+    use crate::kernel::directional::{HeadingDistribution, StepLengthDistribution};
+    use crate::kernel::Kernel;
+
+    #[test]
+    fn test_heading_sectors() {
+        let sectors = HeadingSectors::new(4);
+
+        assert_eq!(sectors.variant(0, -1), 0);
+        assert_eq!(sectors.variant(3, 1), 1);
+        assert_eq!(sectors.variant(-1, 2), 2);
+        assert_eq!(sectors.variant(-5, 0), 3);
+        assert_eq!(sectors.variant(0, 0), 4);
+
+        let sectors = HeadingSectors::new(8);
+
+        assert_eq!(sectors.variant(1, -1), 1);
+        assert_eq!(sectors.variant(1, 1), 3);
+        assert_eq!(sectors.variant(-1, 1), 5);
+        assert_eq!(sectors.variant(-1, -1), 7);
+        assert_eq!(sectors.variant(-1, -3), 0);
+        assert_eq!(sectors.heading(3), 135.0);
+
+        // The center of each sector is mapped to the sector itself
+        for n in 1..=16 {
+            let sectors = HeadingSectors::new(n);
+
+            for sector in 0..n {
+                let (sin, cos) = sectors.heading(sector).to_radians().sin_cos();
+                let (dx, dy) = ((sin * 1000.0) as isize, (-cos * 1000.0) as isize);
+
+                assert_eq!(sectors.variant(dx, dy), sector);
+            }
+        }
+    }
+
+    #[test]
+    fn test_correlated_sectors() {
+        let sectors = HeadingSectors::new(6);
+        let kernels = Kernel::multiple_from_generator(CorrelatedSectorsGenerator {
+            sectors,
+            heading_distribution: HeadingDistribution::WrappedCauchy { concentration: 0.7 },
+            step_length: StepLengthDistribution::Weibull {
+                shape: 1.5,
+                scale: 2.0,
+            },
+            size: 9,
+        })
+        .unwrap();
+
+        assert_eq!(kernels.len(), 7);
+
+        for (variant, kernel) in kernels.iter().enumerate() {
+            assert_eq!(kernel.size(), 9);
+            assert!((kernel.sum() - 1.0).abs() < 1e-12);
+
+            if variant < sectors.sectors() {
+                // The most likely movement of each kernel lies in its own sector
+                let mut max = (0.0, 0);
+
+                for x in -4..=4 {
+                    for y in -4..=4 {
+                        if kernel.at(x, y) > max.0 {
+                            max = (kernel.at(x, y), sectors.variant(x, y));
+                        }
+                    }
+                }
+
+                assert_eq!(max.1, variant);
+            }
+        }
+
+        // The stay kernel has no preferred heading
+        let stay = &kernels[sectors.sectors()];
+
+        assert!((stay.at(2, 0) - stay.at(-2, 0)).abs() < 1e-12);
+        assert!((stay.at(0, 2) - stay.at(0, -2)).abs() < 1e-12);
+    }
+}
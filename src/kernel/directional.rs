@@ -0,0 +1,322 @@
+//! Provides a generator for kernels with a bias towards an arbitrary heading.
+//!
+//! The [`DirectionalGenerator`] combines a distribution of step lengths with a distribution of
+//! headings and rasterizes the resulting movement distribution onto a kernel. Headings are given
+//! as bearings in degrees, measured clockwise from north, where north is the negative y direction.
+//! A heading of `90.0` therefore biases walks towards the east.
+//!
+//! The following heading distributions are available:
+//!
+//! - [`HeadingDistribution::VonMises`]: The von Mises distribution with a concentration `κ >= 0`.
+//! A concentration of `0.0` results in uniformly distributed headings.
+//! - [`HeadingDistribution::WrappedCauchy`]: The wrapped Cauchy distribution with a concentration
+//! `0 <= ρ < 1`. A concentration of `0.0` results in uniformly distributed headings.
+//!
+//! The following step length distributions are available:
+//!
+//! - [`StepLengthDistribution::Gamma`]: The gamma distribution with a shape and a scale.
+//! - [`StepLengthDistribution::Weibull`]: The Weibull distribution with a shape and a scale.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::kernel::directional::{
+//!     DirectionalGenerator, HeadingDistribution, StepLengthDistribution,
+//! };
+//! use randomwalks_lib::kernel::Kernel;
+//!
+//! // Heading north-east with a mean step length of 2
+//! let kernel = Kernel::from_generator(DirectionalGenerator {
+//!     heading: 45.0,
+//!     heading_distribution: HeadingDistribution::VonMises { concentration: 2.0 },
+//!     step_length: StepLengthDistribution::Gamma {
+//!         shape: 2.0,
+//!         scale: 1.0,
+//!     },
+//!     size: 15,
+//! })
+//! .unwrap();
+//! ```
+
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{Continuous, Gamma, Weibull};
+
+use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
+use crate::kernel::Kernel;
+
+/// Number of sample points per axis used to rasterize a single field of the kernel.
+const SUBSAMPLES: usize = 8;
+
+/// A distribution of headings around the mean heading.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HeadingDistribution {
+    VonMises { concentration: f64 },
+    WrappedCauchy { concentration: f64 },
+}
+
+/// A distribution of step lengths.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StepLengthDistribution {
+    Gamma { shape: f64, scale: f64 },
+    Weibull { shape: f64, scale: f64 },
+}
+
+pub struct DirectionalGenerator {
+    pub heading: f64,
+    pub heading_distribution: HeadingDistribution,
+    pub step_length: StepLengthDistribution,
+    pub size: usize,
+}
+
+impl HeadingDistribution {
+    /// Returns the unnormalized density of a heading that deviates by `angle` radians from the
+    /// mean heading.
+    fn density(&self, angle: f64) -> f64 {
+        match *self {
+            HeadingDistribution::VonMises { concentration } => (concentration * angle.cos()).exp(),
+            HeadingDistribution::WrappedCauchy { concentration } => {
+                (1.0 - concentration.powi(2))
+                    / (2.0 * PI * (1.0 + concentration.powi(2) - 2.0 * concentration * angle.cos()))
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), KernelGeneratorError> {
+        match *self {
+            HeadingDistribution::VonMises { concentration } => {
+                if !concentration.is_finite() || concentration < 0.0 {
+                    return Err(KernelGeneratorError::InvalidParameter(
+                        "von Mises concentration must be finite and non-negative".into(),
+                    ));
+                }
+            }
+            HeadingDistribution::WrappedCauchy { concentration } => {
+                if !(0.0..1.0).contains(&concentration) {
+                    return Err(KernelGeneratorError::InvalidParameter(
+                        "wrapped Cauchy concentration must be in [0, 1)".into(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl StepLengthDistribution {
+    /// Returns a function computing the density of a step length.
+    fn density(&self) -> Result<Box<dyn Fn(f64) -> f64>, KernelGeneratorError> {
+        let invalid = |_| {
+            KernelGeneratorError::InvalidParameter(
+                "step length shape and scale must be finite and positive".into(),
+            )
+        };
+
+        match *self {
+            StepLengthDistribution::Gamma { shape, scale } => {
+                let distribution = Gamma::new(shape, 1.0 / scale).map_err(invalid)?;
+
+                Ok(Box::new(move |r| distribution.pdf(r)))
+            }
+            StepLengthDistribution::Weibull { shape, scale } => {
+                let distribution = Weibull::new(shape, scale).map_err(invalid)?;
+
+                Ok(Box::new(move |r| distribution.pdf(r)))
+            }
+        }
+    }
+}
+
+impl KernelGenerator for DirectionalGenerator {
+    fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        if !self.heading.is_finite() {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "heading must be finite".into(),
+            ));
+        }
+
+        // Validate parameters of both distributions
+        self.heading_distribution.validate()?;
+        let _ = self.step_length.density()?;
+
+        kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?
+            .initialize(self.size)?;
+
+        Ok(())
+    }
+
+    fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        let kernel = kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?;
+
+        let step_length = self.step_length.density()?;
+        let heading = self.heading.to_radians();
+        let radius = (self.size / 2) as isize;
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                let mut sum = 0.0;
+
+                // Integrate the density over the field by sampling it at multiple points
+                for i in 0..SUBSAMPLES {
+                    for j in 0..SUBSAMPLES {
+                        let sx = x as f64 + (i as f64 + 0.5) / SUBSAMPLES as f64 - 0.5;
+                        let sy = y as f64 + (j as f64 + 0.5) / SUBSAMPLES as f64 - 0.5;
+
+                        let r = (sx * sx + sy * sy).sqrt();
+                        let bearing = sx.atan2(-sy);
+
+                        // Transform the density from polar into cartesian coordinates
+                        sum += step_length(r)
+                            * self.heading_distribution.density(bearing - heading)
+                            / r;
+                    }
+                }
+
+                kernel.set(x, y, sum);
+            }
+        }
+
+        // Normalize values so that they sum up to 1.0
+        let sum = kernel.sum();
+
+        if sum == 0.0 || !sum.is_finite() {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "distribution has no mass inside of the kernel".into(),
+            ));
+        }
+
+        for x in 0..self.size {
+            for y in 0..self.size {
+                kernel.probabilities[x][y] /= sum;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generates_qty(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> (String, String) {
+        ("dir".into(), "Directional".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::directional::{
+        DirectionalGenerator, HeadingDistribution, StepLengthDistribution,
+    };
+    use crate::kernel::Kernel;
+
+    /// Returns the mean displacement and the mean step length of the kernel.
+    fn means(kernel: &Kernel) -> (f64, f64, f64) {
+        let radius = (kernel.size() / 2) as isize;
+        let (mut mx, mut my, mut mr) = (0.0, 0.0, 0.0);
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                let p = kernel.at(x, y);
+
+                mx += x as f64 * p;
+                my += y as f64 * p;
+                mr += ((x * x + y * y) as f64).sqrt() * p;
+            }
+        }
+
+        (mx, my, mr)
+    }
+
+    #[test]
+    fn test_directional_headings() {
+        for (heading, (ex, ey)) in [
+            (0.0, (0.0, -1.0)),
+            (90.0, (1.0, 0.0)),
+            (180.0, (0.0, 1.0)),
+            (135.0, (1.0, 1.0)),
+        ] {
+            let kernel = Kernel::from_generator(DirectionalGenerator {
+                heading,
+                heading_distribution: HeadingDistribution::VonMises { concentration: 3.0 },
+                step_length: StepLengthDistribution::Gamma {
+                    shape: 4.0,
+                    scale: 0.5,
+                },
+                size: 21,
+            })
+            .unwrap();
+            let (mx, my, _) = means(&kernel);
+
+            assert!((kernel.sum() - 1.0).abs() < 1e-12);
+            assert!(mx * ex >= 0.0 && my * ey >= 0.0);
+            assert!((mx * ey - my * ex).abs() < 1e-9);
+            assert!(mx.hypot(my) > 1.0);
+        }
+    }
+
+    #[test]
+    fn test_directional_step_length() {
+        let kernel = Kernel::from_generator(DirectionalGenerator {
+            heading: 0.0,
+            heading_distribution: HeadingDistribution::WrappedCauchy { concentration: 0.0 },
+            step_length: StepLengthDistribution::Weibull {
+                shape: 2.0,
+                scale: 3.0,
+            },
+            size: 31,
+        })
+        .unwrap();
+        let (mx, my, mr) = means(&kernel);
+
+        // Uniform headings are symmetric
+        assert!(mx.abs() < 1e-9 && my.abs() < 1e-9);
+        assert!((kernel.at(3, 1) - kernel.at(-1, 3)).abs() < 1e-12);
+
+        // Mean of the Weibull distribution is scale * Γ(1 + 1 / shape)
+        assert!((mr - 3.0 * 0.886227).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_directional_invalid() {
+        let generator = |heading_distribution, step_length| DirectionalGenerator {
+            heading: 0.0,
+            heading_distribution,
+            step_length,
+            size: 5,
+        };
+
+        assert!(Kernel::from_generator(generator(
+            HeadingDistribution::WrappedCauchy { concentration: 1.0 },
+            StepLengthDistribution::Gamma {
+                shape: 1.0,
+                scale: 1.0
+            },
+        ))
+        .is_err());
+        assert!(Kernel::from_generator(generator(
+            HeadingDistribution::VonMises {
+                concentration: -1.0
+            },
+            StepLengthDistribution::Gamma {
+                shape: 1.0,
+                scale: 1.0
+            },
+        ))
+        .is_err());
+        assert!(Kernel::from_generator(generator(
+            HeadingDistribution::VonMises { concentration: 1.0 },
+            StepLengthDistribution::Weibull {
+                shape: 0.0,
+                scale: 1.0
+            },
+        ))
+        .is_err());
+    }
+}
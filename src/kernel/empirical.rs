@@ -0,0 +1,347 @@
+//! Provides a generator for kernels estimated from observed steps.
+//!
+//! The [`EmpiricalGenerator`] takes a set of gridded tracks in the form of [`Walk`]s in which
+//! consecutive points are exactly one time step apart, e.g. high-frequency GPS tracks converted
+//! using [`Dataset::to_walks()`]. The probability of each field of the kernel is the relative
+//! frequency of the corresponding displacement among all steps. Steps that are longer than the
+//! kernel are discarded.
+//!
+//! Optionally, the frequencies can be smoothed using a Gaussian kernel with a `bandwidth` given
+//! as standard deviation in fields. This avoids zero probabilities for displacements that are
+//! possible but were not observed.
+//!
+//! Using [`kernel_set()`](EmpiricalGenerator::kernel_set), a separate kernel is estimated for each
+//! field type from all steps starting on a field of that type. Note that dynamic programs choose
+//! the kernel by the field type of the field that is entered by default. Use
+//! [`KernelSelection::Source`](crate::dp::transition::KernelSelection::Source) to choose it by the
+//! field that is left instead.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::dataset::point::XYPoint;
+//! use randomwalks_lib::kernel::empirical::EmpiricalGenerator;
+//! use randomwalks_lib::kernel::Kernel;
+//! use randomwalks_lib::walk::Walk;
+//! use randomwalks_lib::xy;
+//!
+//! let walks = vec![Walk(vec![xy!(0, 0), xy!(1, 0), xy!(2, 0), xy!(2, 1), xy!(3, 1)])];
+//! let kernel = Kernel::from_generator(EmpiricalGenerator {
+//!     walks,
+//!     size: 3,
+//!     bandwidth: None,
+//! })
+//! .unwrap();
+//!
+//! assert_eq!(kernel.at(1, 0), 0.75);
+//! assert_eq!(kernel.at(0, 1), 0.25);
+//! ```
+
+use std::collections::BTreeSet;
+
+use crate::dataset::point::XYPoint;
+use crate::dataset::Dataset;
+use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
+use crate::kernel::Kernel;
+use crate::walk::Walk;
+
+pub struct EmpiricalGenerator {
+    pub walks: Vec<Walk>,
+    pub size: usize,
+    pub bandwidth: Option<f64>,
+}
+
+impl EmpiricalGenerator {
+    /// Creates a generator from the tracks of a dataset in XY coordinates. If `split_by` is
+    /// `Some`, a separate track is created for each value of the given metadata key, see
+    /// [`Dataset::to_walks()`].
+    pub fn from_dataset(
+        dataset: &Dataset,
+        split_by: Option<&str>,
+        size: usize,
+        bandwidth: Option<f64>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            walks: dataset.to_walks(split_by)?,
+            size,
+            bandwidth,
+        })
+    }
+
+    /// Estimates a kernel for each field type.
+    ///
+    /// `field_types` uses the same layout as
+    /// [`DynamicProgramBuilder::field_types()`](crate::dp::builder::DynamicProgramBuilder::field_types),
+    /// i.e. the origin lies in its center. Each step is assigned to the field type of the field
+    /// it starts on. Steps starting outside of `field_types` are ignored. Field types without any
+    /// observed steps get the kernel estimated from all steps.
+    ///
+    /// The returned kernel set can be passed directly to
+    /// [`DynamicProgramBuilder::kernels()`](crate::dp::builder::DynamicProgramBuilder::kernels).
+    pub fn kernel_set(
+        &self,
+        field_types: &[Vec<usize>],
+    ) -> Result<Vec<(usize, Kernel)>, KernelGeneratorError> {
+        let pooled = Kernel::from_generator(self)?;
+        let offset = (field_types.len() / 2) as i64;
+        let field_type = |p: XYPoint| {
+            let (x, y) = (p.x + offset, p.y + offset);
+
+            if x < 0 || y < 0 {
+                return None;
+            }
+
+            field_types.get(x as usize)?.get(y as usize).copied()
+        };
+
+        let types: BTreeSet<usize> = field_types.iter().flatten().copied().collect();
+        let mut kernels = Vec::new();
+
+        for t in types {
+            let steps = self
+                .steps()
+                .filter(|(origin, _)| field_type(*origin) == Some(t))
+                .map(|(_, step)| step);
+
+            let kernel = match self.estimate(steps) {
+                Ok(kernel) => kernel,
+                Err(_) => pooled.clone(),
+            };
+
+            kernels.push((t, kernel));
+        }
+
+        Ok(kernels)
+    }
+
+    /// Returns the origin and the displacement of all steps of all walks.
+    fn steps(&self) -> impl Iterator<Item = (XYPoint, XYPoint)> + '_ {
+        self.walks
+            .iter()
+            .flat_map(|walk| walk.0.windows(2).map(|step| (step[0], step[1] - step[0])))
+    }
+
+    /// Estimates a kernel from the given displacements.
+    fn estimate(
+        &self,
+        steps: impl Iterator<Item = XYPoint>,
+    ) -> Result<Kernel, KernelGeneratorError> {
+        let mut kernel = Kernel {
+            probabilities: vec![vec![0.0; self.size]; self.size],
+            sparse: None,
+            name: self.name(),
+            lattice: self.lattice(),
+        };
+        let radius = (self.size / 2) as i64;
+
+        for step in steps {
+            if step.x.abs() <= radius && step.y.abs() <= radius {
+                let (x, y) = (step.x as isize, step.y as isize);
+
+                kernel.set(x, y, kernel.at(x, y) + 1.0);
+            }
+        }
+
+        if let Some(bandwidth) = self.bandwidth {
+            kernel = smooth(&kernel, bandwidth);
+        }
+
+        let sum = kernel.sum();
+
+        if sum == 0.0 {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "no observed steps inside of the kernel".into(),
+            ));
+        }
+
+        for x in 0..self.size {
+            for y in 0..self.size {
+                kernel.probabilities[x][y] /= sum;
+            }
+        }
+
+        kernel.update_representation();
+
+        Ok(kernel)
+    }
+}
+
+/// Smoothes the frequencies of a kernel using a Gaussian kernel with the given standard deviation.
+fn smooth(counts: &Kernel, bandwidth: f64) -> Kernel {
+    let mut smoothed = counts.clone();
+    let radius = (counts.size() / 2) as isize;
+
+    for x in -radius..=radius {
+        for y in -radius..=radius {
+            let mut sum = 0.0;
+
+            for i in -radius..=radius {
+                for j in -radius..=radius {
+                    let distance = ((x - i).pow(2) + (y - j).pow(2)) as f64;
+
+                    sum += counts.at(i, j) * (-distance / (2.0 * bandwidth * bandwidth)).exp();
+                }
+            }
+
+            smoothed.set(x, y, sum);
+        }
+    }
+
+    smoothed
+}
+
+impl KernelGenerator for EmpiricalGenerator {
+    fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        if let Some(bandwidth) = self.bandwidth {
+            if !bandwidth.is_finite() || bandwidth <= 0.0 {
+                return Err(KernelGeneratorError::InvalidParameter(
+                    "bandwidth must be finite and positive".into(),
+                ));
+            }
+        }
+
+        kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?
+            .initialize(self.size)?;
+
+        Ok(())
+    }
+
+    fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        let kernel = kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?;
+
+        *kernel = self.estimate(self.steps().map(|(_, step)| step))?;
+
+        Ok(())
+    }
+
+    fn generates_qty(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> (String, String) {
+        ("emp".into(), "Empirical".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::dataset::loader::CoordinateType;
+    use crate::dataset::point::{Point, XYPoint};
+    use crate::dataset::{Datapoint, Dataset};
+    use crate::kernel::empirical::EmpiricalGenerator;
+    use crate::kernel::Kernel;
+    use crate::walk::Walk;
+    use crate::xy;
+
+    #[test]
+    fn test_empirical() {
+        let walks = vec![
+            Walk(vec![xy!(0, 0), xy!(1, 0), xy!(1, 0), xy!(3, 0)]),
+            Walk(vec![xy!(5, 5), xy!(5, 4)]),
+        ];
+        let kernel = Kernel::from_generator(EmpiricalGenerator {
+            walks: walks.clone(),
+            size: 3,
+            bandwidth: None,
+        })
+        .unwrap();
+
+        // The step of length 2 is discarded
+        assert_eq!(kernel.size(), 3);
+        assert!((kernel.at(1, 0) - 1.0 / 3.0).abs() < 1e-12);
+        assert!((kernel.at(0, 0) - 1.0 / 3.0).abs() < 1e-12);
+        assert!((kernel.at(0, -1) - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(kernel.at(-1, 0), 0.0);
+
+        let smoothed = Kernel::from_generator(EmpiricalGenerator {
+            walks,
+            size: 5,
+            bandwidth: Some(1.0),
+        })
+        .unwrap();
+
+        assert!((smoothed.sum() - 1.0).abs() < 1e-12);
+        assert!(smoothed.at(-1, 0) > 0.0);
+        assert!(smoothed.at(1, 0) > smoothed.at(-1, 0));
+        assert!(smoothed.at(2, 0) > 0.0);
+    }
+
+    #[test]
+    fn test_empirical_invalid() {
+        let walks = vec![Walk(vec![xy!(0, 0), xy!(4, 0)])];
+
+        assert!(Kernel::from_generator(EmpiricalGenerator {
+            walks: walks.clone(),
+            size: 3,
+            bandwidth: None,
+        })
+        .is_err());
+        assert!(Kernel::from_generator(EmpiricalGenerator {
+            walks,
+            size: 9,
+            bandwidth: Some(0.0),
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_empirical_kernel_set() {
+        // Field type 1 in the east half, field type 0 elsewhere
+        let mut field_types = vec![vec![0; 5]; 5];
+        field_types[3..]
+            .iter_mut()
+            .for_each(|column| column.fill(1));
+        field_types[0][0] = 2;
+
+        let generator = EmpiricalGenerator {
+            walks: vec![Walk(vec![
+                xy!(-1, 0),
+                xy!(0, 0),
+                xy!(1, 0),
+                xy!(1, 1),
+                xy!(2, 1),
+                xy!(2, 0),
+            ])],
+            size: 3,
+            bandwidth: None,
+        };
+        let kernels = generator.kernel_set(&field_types).unwrap();
+
+        assert_eq!(kernels.len(), 3);
+        assert_eq!(kernels[0].0, 0);
+        assert_eq!(kernels[0].1.at(1, 0), 1.0);
+        assert_eq!(kernels[1].0, 1);
+        assert!((kernels[1].1.at(0, 1) - 1.0 / 3.0).abs() < 1e-12);
+        assert!((kernels[1].1.at(1, 0) - 1.0 / 3.0).abs() < 1e-12);
+        assert!((kernels[1].1.at(0, -1) - 1.0 / 3.0).abs() < 1e-12);
+
+        // Field type 2 has no observed steps and gets the pooled kernel
+        assert_eq!(kernels[2].1, Kernel::from_generator(&generator).unwrap());
+    }
+
+    #[test]
+    fn test_empirical_from_dataset() {
+        let mut dataset = Dataset::new(CoordinateType::XY);
+
+        for (agent, x, y) in [("a", 0, 0), ("b", 5, 5), ("a", 1, 0), ("b", 5, 6)] {
+            dataset.push(Datapoint {
+                point: Point::XY(xy!(x, y)),
+                metadata: HashMap::from([("agent".to_string(), agent.to_string())]),
+            });
+        }
+
+        let kernel = Kernel::from_generator(
+            EmpiricalGenerator::from_dataset(&dataset, Some("agent"), 3, None).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(kernel.at(1, 0), 0.5);
+        assert_eq!(kernel.at(0, 1), 0.5);
+    }
+}
@@ -1,12 +1,45 @@
 use thiserror::Error;
 
 use crate::kernel::Kernel;
+use crate::lattice::Lattice;
 
 pub trait KernelGenerator {
     fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError>;
     fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError>;
     fn generates_qty(&self) -> usize;
     fn name(&self) -> (String, String);
+
+    /// Returns the [`Lattice`] the generated kernels are defined on.
+    ///
+    /// Only generators that are parameterised over the lattice, e.g. the
+    /// [`LatticeRwGenerator`](crate::kernel::lattice_rw::LatticeRwGenerator), override this. All
+    /// other generators only generate kernels on [`Lattice::Square`]. Use
+    /// [`Kernel::from_generator_on()`] to reject generators that do not support a given lattice.
+    fn lattice(&self) -> Lattice {
+        Lattice::Square
+    }
+}
+
+impl<G: KernelGenerator + ?Sized> KernelGenerator for &G {
+    fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        (**self).prepare(kernels)
+    }
+
+    fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        (**self).generate(kernels)
+    }
+
+    fn generates_qty(&self) -> usize {
+        (**self).generates_qty()
+    }
+
+    fn name(&self) -> (String, String) {
+        (**self).name()
+    }
+
+    fn lattice(&self) -> Lattice {
+        (**self).lattice()
+    }
 }
 
 #[derive(Error, Debug)]
@@ -17,4 +50,18 @@ pub enum KernelGeneratorError {
     NotEnoughKernels,
     #[error("kernel size must be odd")]
     SizeEven,
+    #[error("invalid parameter: {0}")]
+    InvalidParameter(String),
+    #[error("covariance matrix must be symmetric")]
+    CovarianceNotSymmetric,
+    #[error("covariance matrix must be positive-definite")]
+    CovarianceNotPositiveDefinite,
+    #[error("kernels must have the same size, found sizes {0} and {1}")]
+    SizeMismatch(usize, usize),
+    #[error("kernels must be defined on the same lattice")]
+    LatticeMismatch,
+    #[error("the generator does not support kernels on a {0:?} lattice")]
+    UnsupportedLattice(Lattice),
+    #[error("invalid kernel generator spec: {0}")]
+    InvalidSpec(String),
 }
@@ -1,9 +1,11 @@
+use serde::{Deserialize, Serialize};
 use statrs::distribution::{Continuous, MultivariateNormal};
 
 use crate::dataset::point::XYPoint;
 use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
 use crate::kernel::Kernel;
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HalfNormalDistSide {
     Left,
     Right,
@@ -45,8 +47,8 @@ impl KernelGenerator for HalfNormalDistGenerator {
             .ok_or(KernelGeneratorError::OneKernelRequired)?;
 
         let mean = vec![
-            (self.size / 2 + self.mean.x as usize) as f64,
-            (self.size / 2 + self.mean.y as usize) as f64,
+            (self.size / 2) as f64 + self.mean.x as f64,
+            (self.size / 2) as f64 + self.mean.y as f64,
         ];
         let cov = vec![self.diffusion, 0.0, 0.0, self.diffusion];
         let distribution = MultivariateNormal::new(mean, cov).unwrap();
@@ -114,6 +116,6 @@ impl KernelGenerator for HalfNormalDistGenerator {
     }
 
     fn name(&self) -> (String, String) {
-        ("nd".into(), "Normal Distribution".into())
+        ("hnd".into(), "Half Normal Distribution".into())
     }
 }
@@ -0,0 +1,225 @@
+//! Provides functions for reading and writing kernels and kernel sets.
+//!
+//! Kernels implement [`Serialize`](serde::Serialize) and [`Deserialize`](serde::Deserialize) and
+//! can therefore be stored in any format supported by serde. Their probabilities are serialized as
+//! a list of columns, i.e. `probabilities[x][y]`, together with their `(short, long)` name and
+//! their [`Lattice`](crate::lattice::Lattice).
+//!
+//! Additionally, two file formats are supported directly:
+//!
+//! - A plain text matrix using [`Kernel::save_matrix()`] and [`Kernel::load_matrix()`]. Each line
+//! contains one row of the kernel from north to south, i.e. the same layout as used by the
+//! [`kernel!`](crate::kernel!) macro. Values are separated by commas or whitespace, so CSV files
+//! can be read as well. Empty lines and lines starting with `#` are ignored.
+//! - JSON using [`Kernel::save_json()`] and [`Kernel::load_json()`].
+//!
+//! Kernel sets, i.e. a kernel for each field type, can be stored as a JSON object mapping field
+//! types to kernels using [`save_kernel_set()`] and [`load_kernel_set()`]. A loaded kernel set can
+//! be passed directly to [`DynamicProgramBuilder::kernels()`](crate::dp::builder::DynamicProgramBuilder::kernels).
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
+//! use randomwalks_lib::kernel::Kernel;
+//!
+//! let kernel = Kernel::from_generator(SimpleRwGenerator).unwrap();
+//! let matrix = kernel.to_matrix_string();
+//!
+//! assert_eq!(matrix, "0,0.2,0\n0.2,0.2,0.2\n0,0.2,0\n");
+//! assert_eq!(Kernel::from_matrix_str(&matrix).unwrap(), kernel);
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::{bail, Context};
+
+use crate::kernel::Kernel;
+
+impl Kernel {
+    /// Returns the kernel as a plain text matrix with comma-separated values and one row per line.
+    pub fn to_matrix_string(&self) -> String {
+        let mut res = String::new();
+        let radius = (self.size() / 2) as isize;
+
+        for y in -radius..=radius {
+            let row: Vec<String> = (-radius..=radius)
+                .map(|x| self.at(x, y).to_string())
+                .collect();
+
+            res += &row.join(",");
+            res += "\n";
+        }
+
+        res
+    }
+
+    /// Parses a kernel from a plain text matrix with one row per line. Values can be separated by
+    /// commas or whitespace.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value cannot be parsed or if the matrix is not square with an odd size.
+    pub fn from_matrix_str(matrix: &str) -> anyhow::Result<Kernel> {
+        let mut rows = Vec::new();
+
+        for (i, line) in matrix.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let row = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("invalid value in line {}", i + 1))?;
+
+            rows.push(row);
+        }
+
+        let size = rows.len();
+
+        if size % 2 == 0 {
+            bail!("kernel size must be odd, found {size} rows");
+        }
+
+        if let Some(row) = rows.iter().find(|row| row.len() != size) {
+            bail!(
+                "kernel must be square, found a row of length {} in {size} rows",
+                row.len()
+            );
+        }
+
+        let mut kernel = Kernel::try_from_value(size, 0.0)?;
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, probability) in row.iter().enumerate() {
+                kernel.probabilities[x][y] = *probability;
+            }
+        }
+
+        kernel.update_representation();
+
+        Ok(kernel)
+    }
+
+    /// Writes the kernel to a plain text matrix file, see
+    /// [`to_matrix_string()`](Kernel::to_matrix_string).
+    pub fn save_matrix<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_matrix_string())?;
+
+        Ok(())
+    }
+
+    /// Reads a kernel from a plain text matrix file, see
+    /// [`from_matrix_str()`](Kernel::from_matrix_str).
+    pub fn load_matrix<P: AsRef<Path>>(path: P) -> anyhow::Result<Kernel> {
+        Kernel::from_matrix_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Writes the kernel to a JSON file.
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+
+        Ok(())
+    }
+
+    /// Reads a kernel from a JSON file.
+    pub fn load_json<P: AsRef<Path>>(path: P) -> anyhow::Result<Kernel> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+/// Writes a kernel set to a JSON file as an object mapping field types to kernels.
+pub fn save_kernel_set<P: AsRef<Path>>(kernels: &[(usize, Kernel)], path: P) -> anyhow::Result<()> {
+    let map: BTreeMap<usize, &Kernel> = kernels
+        .iter()
+        .map(|(field_type, kernel)| (*field_type, kernel))
+        .collect();
+
+    if map.len() != kernels.len() {
+        bail!("field types of a kernel set must be unique");
+    }
+
+    serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &map)?;
+
+    Ok(())
+}
+
+/// Reads a kernel set from a JSON file. The kernels are sorted by their field types.
+pub fn load_kernel_set<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<(usize, Kernel)>> {
+    let map: BTreeMap<usize, Kernel> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+
+    Ok(map.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::io::{load_kernel_set, save_kernel_set};
+    use crate::kernel::lattice_rw::LatticeRwGenerator;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::Kernel;
+    use crate::lattice::Lattice;
+
+    #[test]
+    fn test_matrix() {
+        let kernel =
+            Kernel::from_matrix_str("# comment\n0.1, 0.2, 0.3\n0.4 0.5 0.6\n\n0.7,0.8,0.9\n")
+                .unwrap();
+
+        assert_eq!(kernel.at(1, -1), 0.3);
+        assert_eq!(kernel.at(-1, 1), 0.7);
+        assert_eq!(
+            Kernel::from_matrix_str(&kernel.to_matrix_string()).unwrap(),
+            kernel
+        );
+
+        assert!(Kernel::from_matrix_str("1,2\n3,4").is_err());
+        assert!(Kernel::from_matrix_str("1,2,3\n4,5\n6,7,8").is_err());
+        assert!(Kernel::from_matrix_str("1,2,3\n4,x,6\n7,8,9").is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let kernel = Kernel::from_generator(LatticeRwGenerator {
+            lattice: Lattice::Hexagonal,
+        })
+        .unwrap();
+        let json = serde_json::to_string(&kernel).unwrap();
+        let deserialized: Kernel = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized, kernel);
+        assert_eq!(deserialized.name(true), "lrw");
+        assert_eq!(deserialized.lattice(), Lattice::Hexagonal);
+
+        // Kernels that are not square or have an even size are rejected
+        let invalid = r#"{"probabilities":[[1.0],[1.0,2.0]],"name":["",""]}"#;
+
+        assert!(serde_json::from_str::<Kernel>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_kernel_set() {
+        let path = std::env::temp_dir().join("randomwalks_test_kernel_set.json");
+        let kernels: Vec<(usize, Kernel)> = vec![
+            (3, Kernel::try_from_value(3, 1.0 / 9.0).unwrap()),
+            (0, Kernel::from_generator(SimpleRwGenerator).unwrap()),
+        ];
+
+        save_kernel_set(&kernels, &path).unwrap();
+        let loaded = load_kernel_set(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0], kernels[1]);
+        assert_eq!(loaded[1], kernels[0]);
+
+        assert!(save_kernel_set(&[kernels[0].clone(), kernels[0].clone()], &path).is_err());
+    }
+}
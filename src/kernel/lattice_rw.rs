@@ -0,0 +1,110 @@
+//! Provides a generator for simple random walks on arbitrary [`Lattice`]s.
+//!
+//! The generated kernel assigns the same probability to staying and to each single step that is
+//! possible on the lattice. On [`Lattice::Square`], this is equal to the kernel generated by
+//! [`SimpleRwGenerator`](crate::kernel::simple_rw::SimpleRwGenerator).
+
+use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
+use crate::kernel::Kernel;
+use crate::lattice::Lattice;
+
+pub struct LatticeRwGenerator {
+    pub lattice: Lattice,
+}
+
+impl KernelGenerator for LatticeRwGenerator {
+    fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?
+            .initialize(3)
+            .unwrap();
+
+        Ok(())
+    }
+
+    fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        let kernel = kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?;
+        let neighbors = self.lattice.neighbors();
+
+        for &(x, y) in neighbors.iter() {
+            kernel.set(x, y, 1.0 / neighbors.len() as f64);
+        }
+
+        Ok(())
+    }
+
+    fn generates_qty(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> (String, String) {
+        ("lrw".into(), "Lattice RW".into())
+    }
+
+    fn lattice(&self) -> Lattice {
+        self.lattice
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::correlated_rw::CorrelatedRwGenerator;
+    use crate::kernel::generator::KernelGeneratorError;
+    use crate::kernel::lattice_rw::LatticeRwGenerator;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::Kernel;
+    use crate::lattice::Lattice;
+
+    #[test]
+    fn test_lattice_rw() {
+        let square = Kernel::from_generator(LatticeRwGenerator {
+            lattice: Lattice::Square,
+        })
+        .unwrap();
+
+        assert_eq!(square, Kernel::from_generator(SimpleRwGenerator).unwrap());
+
+        let hexagonal = Kernel::from_generator(LatticeRwGenerator {
+            lattice: Lattice::Hexagonal,
+        })
+        .unwrap();
+
+        assert_eq!(hexagonal.lattice(), Lattice::Hexagonal);
+        assert!((hexagonal.sum() - 1.0).abs() < 1e-12);
+        assert_eq!(hexagonal.at(1, 1), 0.0);
+        assert_eq!(hexagonal.at(-1, -1), 0.0);
+        assert!((hexagonal.at(1, -1) - 1.0 / 7.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_generators_on_lattice() {
+        let hexagonal = Kernel::from_generator_on(
+            LatticeRwGenerator {
+                lattice: Lattice::Hexagonal,
+            },
+            Lattice::Hexagonal,
+        )
+        .unwrap();
+
+        assert_eq!(hexagonal.lattice(), Lattice::Hexagonal);
+
+        // Generators that are not parameterised over the lattice only support square lattices
+        assert!(Kernel::from_generator_on(SimpleRwGenerator, Lattice::Square).is_ok());
+        assert!(matches!(
+            Kernel::from_generator_on(SimpleRwGenerator, Lattice::Hexagonal),
+            Err(KernelGeneratorError::UnsupportedLattice(Lattice::Hexagonal))
+        ));
+        assert!(matches!(
+            Kernel::multiple_from_generator_on(
+                CorrelatedRwGenerator { persistence: 0.5 },
+                Lattice::SquareDiagonal
+            ),
+            Err(KernelGeneratorError::UnsupportedLattice(
+                Lattice::SquareDiagonal
+            ))
+        ));
+    }
+}
@@ -0,0 +1,205 @@
+//! Provides a generator for Lévy walk kernels.
+//!
+//! In a Lévy walk, the length `l` of each step follows a truncated power law, i.e.
+//! `P(l) ∝ l^(-exponent)` for `1 <= l <= max_step_size`. All fields whose rounded euclidean
+//! distance to the center equals `l` share the probability of step length `l`. Without a bias,
+//! the probability is distributed uniformly among these fields. With a bias towards a
+//! [`Direction`], each field is weighted by `exp(strength * cos(θ))`, where `θ` is the angle
+//! between the field and the direction.
+//!
+//! The generated kernels have a size of `2 * max_step_size + 1` and can be used with the
+//! [`BacktrackingWalker`](crate::walker::backtracking::BacktrackingWalker).
+
+use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
+use crate::kernel::{Direction, Kernel};
+
+pub struct LevyWalkGenerator {
+    pub exponent: f64,
+    pub max_step_size: usize,
+    pub bias: Option<(Direction, f64)>,
+}
+
+impl KernelGenerator for LevyWalkGenerator {
+    fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        if !self.exponent.is_finite() {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "exponent must be finite".into(),
+            ));
+        }
+
+        if self.max_step_size == 0 {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "max_step_size must be at least 1".into(),
+            ));
+        }
+
+        if let Some((direction, strength)) = self.bias {
+            if direction == Direction::Stay {
+                return Err(KernelGeneratorError::InvalidParameter(
+                    "bias direction must not be Stay".into(),
+                ));
+            }
+
+            if !strength.is_finite() || strength < 0.0 {
+                return Err(KernelGeneratorError::InvalidParameter(
+                    "bias strength must be finite and non-negative".into(),
+                ));
+            }
+        }
+
+        kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?
+            .initialize(2 * self.max_step_size + 1)?;
+
+        Ok(())
+    }
+
+    fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        let kernel = kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?;
+        let max = self.max_step_size as isize;
+
+        // Compute direction weights of all fields and their sums per step length
+        let mut weights = Vec::new();
+        let mut ring_sums = vec![0.0; self.max_step_size + 1];
+
+        for x in -max..=max {
+            for y in -max..=max {
+                let length = ((x * x + y * y) as f64).sqrt().round() as usize;
+
+                if length == 0 || length > self.max_step_size {
+                    continue;
+                }
+
+                let weight = match self.bias {
+                    Some((direction, strength)) => {
+                        let (dx, dy): (isize, isize) = direction.into();
+                        let cos = (x * dx + y * dy) as f64 / ((x * x + y * y) as f64).sqrt();
+
+                        (strength * cos).exp()
+                    }
+                    None => 1.0,
+                };
+
+                weights.push((x, y, length, weight));
+                ring_sums[length] += weight;
+            }
+        }
+
+        // Truncated power law of step lengths
+        let lengths: Vec<f64> = (0..=self.max_step_size)
+            .map(|l| match l {
+                0 => 0.0,
+                l => (l as f64).powf(-self.exponent),
+            })
+            .collect();
+        let norm: f64 = lengths.iter().sum();
+
+        for (x, y, length, weight) in weights {
+            kernel.set(x, y, lengths[length] / norm * weight / ring_sums[length]);
+        }
+
+        Ok(())
+    }
+
+    fn generates_qty(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> (String, String) {
+        ("lw".into(), "Lévy Walk".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::levy_walk::LevyWalkGenerator;
+    use crate::kernel::{Direction, Kernel};
+
+    /// Returns the probability of each step length in the kernel.
+    fn step_lengths(kernel: &Kernel) -> Vec<f64> {
+        let radius = (kernel.size() / 2) as isize;
+        let mut lengths = vec![0.0; radius as usize + 1];
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                let length = ((x * x + y * y) as f64).sqrt().round() as usize;
+
+                if length <= radius as usize {
+                    lengths[length] += kernel.at(x, y);
+                }
+            }
+        }
+
+        lengths
+    }
+
+    #[test]
+    fn test_levy_walk() {
+        let kernel = Kernel::from_generator(LevyWalkGenerator {
+            exponent: 2.0,
+            max_step_size: 5,
+            bias: None,
+        })
+        .unwrap();
+
+        assert_eq!(kernel.size(), 11);
+        assert!((kernel.sum() - 1.0).abs() < 1e-12);
+        assert_eq!(kernel.at(0, 0), 0.0);
+        assert_eq!(kernel.at(5, 5), 0.0);
+        assert_eq!(kernel.at(3, 0), kernel.at(0, -3));
+
+        let lengths = step_lengths(&kernel);
+
+        for l in 1..5 {
+            let ratio = lengths[l] / lengths[l + 1];
+            let expected = ((l + 1) as f64 / l as f64).powi(2);
+
+            assert!((ratio - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_levy_walk_biased() {
+        let unbiased = Kernel::from_generator(LevyWalkGenerator {
+            exponent: 1.5,
+            max_step_size: 4,
+            bias: None,
+        })
+        .unwrap();
+        let biased = Kernel::from_generator(LevyWalkGenerator {
+            exponent: 1.5,
+            max_step_size: 4,
+            bias: Some((Direction::East, 1.0)),
+        })
+        .unwrap();
+
+        assert!((biased.sum() - 1.0).abs() < 1e-12);
+        assert!(biased.at(3, 0) > biased.at(0, 3));
+        assert!(biased.at(0, 3) > biased.at(-3, 0));
+
+        // The bias only affects directions, not step lengths
+        for (a, b) in step_lengths(&unbiased).iter().zip(step_lengths(&biased)) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_levy_walk_invalid() {
+        assert!(Kernel::from_generator(LevyWalkGenerator {
+            exponent: 2.0,
+            max_step_size: 0,
+            bias: None,
+        })
+        .is_err());
+
+        assert!(Kernel::from_generator(LevyWalkGenerator {
+            exponent: 2.0,
+            max_step_size: 3,
+            bias: Some((Direction::Stay, 1.0)),
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Provides a generator for weighted mixtures of other kernel generators.
+//!
+//! A mixture describes movement that follows one of multiple models in each time step, e.g. 80%
+//! resting and 20% travelling. The generated kernel is the weighted sum of the kernels of all
+//! components, see [`Kernel::mixture()`]. Mixtures of existing kernels can be created using
+//! [`Kernel::mixture()`] directly.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::kernel::levy_walk::LevyWalkGenerator;
+//! use randomwalks_lib::kernel::mixture::MixtureGenerator;
+//! use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
+//! use randomwalks_lib::kernel::Kernel;
+//!
+//! let kernel = Kernel::from_generator(MixtureGenerator {
+//!     components: vec![
+//!         (0.8, Box::new(SimpleRwGenerator)),
+//!         (
+//!             0.2,
+//!             Box::new(LevyWalkGenerator {
+//!                 exponent: 2.0,
+//!                 max_step_size: 5,
+//!                 bias: None,
+//!             }),
+//!         ),
+//!     ],
+//! })
+//! .unwrap();
+//!
+//! assert_eq!(kernel.size(), 11);
+//! ```
+
+use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
+use crate::kernel::Kernel;
+use crate::lattice::Lattice;
+
+pub struct MixtureGenerator {
+    pub components: Vec<(f64, Box<dyn KernelGenerator>)>,
+}
+
+impl KernelGenerator for MixtureGenerator {
+    fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        if self
+            .components
+            .iter()
+            .any(|(_, generator)| generator.generates_qty() != 1)
+        {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "all components of a mixture must generate a single kernel".into(),
+            ));
+        }
+
+        kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?;
+
+        Ok(())
+    }
+
+    fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        let kernel = kernels
+            .get_mut(0)
+            .ok_or(KernelGeneratorError::OneKernelRequired)?;
+
+        let components = self
+            .components
+            .iter()
+            .map(|(weight, generator)| Ok((*weight, Kernel::from_generator(generator.as_ref())?)))
+            .collect::<Result<Vec<_>, KernelGeneratorError>>()?;
+
+        *kernel = Kernel::mixture(&components)?;
+
+        Ok(())
+    }
+
+    fn generates_qty(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> (String, String) {
+        ("mix".into(), "Mixture".into())
+    }
+
+    fn lattice(&self) -> Lattice {
+        self.components
+            .first()
+            .map(|(_, generator)| generator.lattice())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel;
+    use crate::kernel::generator::KernelGeneratorError;
+    use crate::kernel::lattice_rw::LatticeRwGenerator;
+    use crate::kernel::mixture::MixtureGenerator;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::Kernel;
+    use crate::lattice::Lattice;
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_mixture_generator() {
+        let kernel = Kernel::from_generator(MixtureGenerator {
+            components: vec![
+                (3.0, Box::new(SimpleRwGenerator)),
+                (1.0, Box::new(SimpleRwGenerator)),
+            ],
+        })
+        .unwrap();
+
+        assert_eq!(kernel, Kernel::from_generator(SimpleRwGenerator).unwrap());
+
+        let resting = kernel![
+            0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0,
+        ];
+        let kernel = Kernel::mixture(&[
+            (0.8, resting),
+            (0.2, Kernel::from_generator(SimpleRwGenerator).unwrap()),
+        ])
+        .unwrap();
+
+        assert!((kernel.at(0, 0) - 0.84).abs() < 1e-12);
+        assert!((kernel.at(1, 0) - 0.04).abs() < 1e-12);
+        assert!((kernel.sum() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mixture_invalid() {
+        assert!(matches!(
+            Kernel::from_generator(MixtureGenerator {
+                components: vec![
+                    (0.5, Box::new(SimpleRwGenerator)),
+                    (
+                        0.5,
+                        Box::new(LatticeRwGenerator {
+                            lattice: Lattice::Hexagonal,
+                        }),
+                    ),
+                ],
+            }),
+            Err(KernelGeneratorError::LatticeMismatch)
+        ));
+
+        assert!(Kernel::mixture(&[]).is_err());
+        assert!(
+            Kernel::mixture(&[(-1.0, Kernel::from_generator(SimpleRwGenerator).unwrap())]).is_err()
+        );
+    }
+}
@@ -6,21 +6,135 @@ use std::ops::{Add, Div, DivAssign, Index, IndexMut, Mul, MulAssign};
 use anyhow::bail;
 use serde::{Deserialize, Serialize};
 use strum::EnumIter;
+use thiserror::Error;
 
+use crate::kernel::correlated_sectors::HeadingSectors;
 use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
+use crate::kernel::sparse::SparseKernel;
+use crate::lattice::Lattice;
 
+pub mod anisotropic_normal_dist;
 pub mod biased_correlated_rw;
 pub mod biased_rw;
 pub mod correlated_rw;
+pub mod correlated_sectors;
+pub mod directional;
+pub mod empirical;
 pub mod generator;
 pub mod half_normal_dist;
+pub mod io;
+pub mod lattice_rw;
+pub mod levy_walk;
+pub mod mixture;
 pub mod normal_dist;
+#[cfg(feature = "plotting")]
+pub mod plot;
 pub mod simple_rw;
+pub mod sparse;
+pub mod spec;
+
+/// Maximum deviation of the sum of a valid kernel from 1.0.
+pub const SUM_TOLERANCE: f64 = 1e-9;
+
+/// An error that can occur when validating or normalizing a [`Kernel`].
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum KernelValidationError {
+    /// This error occurs when the kernel contains negative, infinite or NaN probabilities. The
+    /// offending cells are given as `(x, y, probability)` relative to the center of the kernel.
+    #[error("kernel contains {} negative or non-finite probabilities {cells:?}, sum is {sum}", cells.len())]
+    InvalidProbabilities {
+        cells: Vec<(isize, isize, f64)>,
+        sum: f64,
+    },
+
+    /// This error occurs when the probabilities of the kernel do not sum up to 1.0.
+    #[error("kernel probabilities must sum up to 1.0, found {sum}")]
+    NotNormalized { sum: f64 },
+
+    /// This error occurs when a kernel without any probability mass is normalized.
+    #[error("kernel without probability mass cannot be normalized")]
+    ZeroSum,
+}
+
+/// Number of sample points per axis used by [`Interpolation::AreaWeighted`].
+const ROTATION_SUBSAMPLES: usize = 8;
 
-#[derive(Clone)]
+/// Method used to resample probabilities when rotating a kernel by an arbitrary angle using
+/// [`Kernel::rotate_by()`].
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone, EnumIter, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Each field of the rotated kernel takes the bilinearly interpolated probability at the
+    /// corresponding position in the original kernel.
+    #[default]
+    Bilinear,
+
+    /// The probability mass of each field of the original kernel is distributed onto the fields
+    /// of the rotated kernel proportionally to the area they cover.
+    AreaWeighted,
+}
+
+/// A kernel containing the probabilities of all possible steps relative to its center.
+///
+/// Kernels are stored either as a dense matrix or as a list of their non-zero entries, depending
+/// on their density. For details see the [`sparse`] module.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawKernel", into = "RawKernel")]
 pub struct Kernel {
-    pub probabilities: Vec<Vec<f64>>,
+    /// Probabilities indexed by `[x][y]`. Empty if the kernel is stored sparsely.
+    pub(crate) probabilities: Vec<Vec<f64>>,
+    /// The non-zero entries if the kernel is stored sparsely.
+    sparse: Option<SparseKernel>,
+    name: (String, String),
+    lattice: Lattice,
+}
+
+/// A [`Kernel`] as it is serialized, i.e. always using a dense matrix of probabilities. When
+/// deserializing, it has not been checked for a valid size yet.
+#[derive(Serialize, Deserialize)]
+struct RawKernel {
+    probabilities: Vec<Vec<f64>>,
     name: (String, String),
+    #[serde(default)]
+    lattice: Lattice,
+}
+
+impl TryFrom<RawKernel> for Kernel {
+    type Error = KernelGeneratorError;
+
+    fn try_from(raw: RawKernel) -> Result<Self, Self::Error> {
+        let size = raw.probabilities.len();
+
+        if size % 2 == 0 {
+            return Err(KernelGeneratorError::SizeEven);
+        }
+
+        if raw.probabilities.iter().any(|column| column.len() != size) {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "kernel must be square".into(),
+            ));
+        }
+
+        let mut kernel = Kernel {
+            probabilities: raw.probabilities,
+            sparse: None,
+            name: raw.name,
+            lattice: raw.lattice,
+        };
+
+        kernel.update_representation();
+
+        Ok(kernel)
+    }
+}
+
+impl From<Kernel> for RawKernel {
+    fn from(kernel: Kernel) -> Self {
+        RawKernel {
+            probabilities: kernel.to_dense(),
+            name: kernel.name,
+            lattice: kernel.lattice,
+        }
+    }
 }
 
 impl Kernel {
@@ -31,20 +145,27 @@ impl Kernel {
 
         Ok(Self {
             probabilities: vec![vec![0.0; size]; size],
+            sparse: None,
             name,
+            lattice: Lattice::default(),
         })
     }
 
     pub fn from_generator(generator: impl KernelGenerator) -> Result<Kernel, KernelGeneratorError> {
         let kernel = Kernel {
             probabilities: Vec::new(),
+            sparse: None,
             name: generator.name(),
+            lattice: generator.lattice(),
         };
         let mut kernels = vec![kernel];
 
         generator.prepare(&mut kernels)?;
         generator.generate(&mut kernels)?;
 
+        kernels[0].name = generator.name();
+        kernels[0].update_representation();
+
         Ok(kernels[0].clone())
     }
 
@@ -53,16 +174,59 @@ impl Kernel {
     ) -> Result<Vec<Kernel>, KernelGeneratorError> {
         let kernel = Kernel {
             probabilities: Vec::new(),
+            sparse: None,
             name: generator.name(),
+            lattice: generator.lattice(),
         };
         let mut kernels = vec![kernel.clone(); generator.generates_qty()];
 
         generator.prepare(&mut kernels)?;
         generator.generate(&mut kernels)?;
 
+        // Generators composed of other generators replace the kernels including their names
+        for kernel in kernels.iter_mut() {
+            kernel.name = generator.name();
+            kernel.update_representation();
+        }
+
         Ok(kernels)
     }
 
+    /// Generates a kernel on the given [`Lattice`] like [`from_generator()`](Kernel::from_generator).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KernelGeneratorError::UnsupportedLattice`] if the generator does not generate
+    /// kernels on `lattice`, see [`KernelGenerator::lattice()`].
+    pub fn from_generator_on(
+        generator: impl KernelGenerator,
+        lattice: Lattice,
+    ) -> Result<Kernel, KernelGeneratorError> {
+        if generator.lattice() != lattice {
+            return Err(KernelGeneratorError::UnsupportedLattice(lattice));
+        }
+
+        Kernel::from_generator(generator)
+    }
+
+    /// Generates multiple kernels on the given [`Lattice`] like
+    /// [`multiple_from_generator()`](Kernel::multiple_from_generator).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KernelGeneratorError::UnsupportedLattice`] if the generator does not generate
+    /// kernels on `lattice`, see [`KernelGenerator::lattice()`].
+    pub fn multiple_from_generator_on(
+        generator: impl KernelGenerator,
+        lattice: Lattice,
+    ) -> Result<Vec<Kernel>, KernelGeneratorError> {
+        if generator.lattice() != lattice {
+            return Err(KernelGeneratorError::UnsupportedLattice(lattice));
+        }
+
+        Kernel::multiple_from_generator(generator)
+    }
+
     pub fn try_from_value(size: usize, value: f64) -> anyhow::Result<Self> {
         if size % 2 == 0 {
             bail!("size must be odd")
@@ -70,13 +234,16 @@ impl Kernel {
 
         Ok(Self {
             probabilities: vec![vec![value; size]; size],
+            sparse: None,
             name: (String::new(), String::new()),
+            lattice: Lattice::default(),
         })
     }
 
     pub fn initialize(&mut self, size: usize) -> Result<(), KernelGeneratorError> {
         if size % 2 == 1 {
             self.probabilities = vec![vec![0.0; size]; size];
+            self.sparse = None;
 
             Ok(())
         } else {
@@ -85,10 +252,17 @@ impl Kernel {
     }
 
     pub fn size(&self) -> usize {
-        self.probabilities.len()
+        match &self.sparse {
+            Some(sparse) => 2 * sparse.radius() as usize + 1,
+            None => self.probabilities.len(),
+        }
     }
 
     pub fn sum(&self) -> f64 {
+        if let Some(sparse) = &self.sparse {
+            return sparse.entries().iter().map(|(_, _, p)| p).sum();
+        }
+
         let mut sum = 0.0;
 
         for x in 0..self.size() {
@@ -100,25 +274,120 @@ impl Kernel {
         sum
     }
 
+    /// Checks that all probabilities of the kernel are finite and non-negative and that they sum
+    /// up to 1.0 within [`SUM_TOLERANCE`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`KernelValidationError`] listing all offending cells or the sum of the kernel.
+    pub fn validate(&self) -> Result<(), KernelValidationError> {
+        let cells = self.invalid_cells();
+        let sum = self.sum();
+
+        if !cells.is_empty() {
+            return Err(KernelValidationError::InvalidProbabilities { cells, sum });
+        }
+
+        if (sum - 1.0).abs() > SUM_TOLERANCE {
+            return Err(KernelValidationError::NotNormalized { sum });
+        }
+
+        Ok(())
+    }
+
+    /// Divides all probabilities of the kernel by their sum so that they sum up to 1.0.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`KernelValidationError`] if the kernel contains negative or non-finite
+    /// probabilities or if all probabilities are zero. The kernel is left unchanged in that case.
+    pub fn normalize(&mut self) -> Result<(), KernelValidationError> {
+        let cells = self.invalid_cells();
+        let sum = self.sum();
+
+        if !cells.is_empty() {
+            return Err(KernelValidationError::InvalidProbabilities { cells, sum });
+        }
+
+        if sum == 0.0 {
+            return Err(KernelValidationError::ZeroSum);
+        }
+
+        if let Some(sparse) = &mut self.sparse {
+            sparse.scale(1.0 / sum);
+        }
+
+        for column in self.probabilities.iter_mut() {
+            for probability in column.iter_mut() {
+                *probability /= sum;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns all cells containing negative or non-finite probabilities.
+    fn invalid_cells(&self) -> Vec<(isize, isize, f64)> {
+        self.support()
+            .into_iter()
+            .filter(|(_, _, probability)| !probability.is_finite() || *probability < 0.0)
+            .collect()
+    }
+
+    /// Sets the probability at `(x, y)`. The representation of the kernel is kept, see
+    /// [`update_representation()`](Kernel::update_representation).
     pub fn set(&mut self, x: isize, y: isize, val: f64) {
+        if let Some(sparse) = &mut self.sparse {
+            sparse.set(x, y, val);
+
+            return;
+        }
+
         let x = ((self.probabilities.len() / 2) as isize + x) as usize;
         let y = ((self.probabilities.len() / 2) as isize + y) as usize;
 
         self.probabilities[x][y] = val;
     }
 
+    /// Returns all probabilities of the kernel indexed by `[x][y]`, independently of its
+    /// [`KernelRepresentation`](crate::kernel::sparse::KernelRepresentation). Replaces the
+    /// formerly public `probabilities` field, see [`to_dense()`](Kernel::to_dense).
+    pub fn probabilities(&self) -> Vec<Vec<f64>> {
+        self.to_dense()
+    }
+
     pub fn at(&self, x: isize, y: isize) -> f64 {
+        if let Some(sparse) = &self.sparse {
+            return sparse.at(x, y);
+        }
+
         let x = ((self.probabilities.len() / 2) as isize + x) as usize;
         let y = ((self.probabilities.len() / 2) as isize + y) as usize;
 
         self.probabilities[x][y]
     }
 
+    /// Returns the probability at `(x, y)` or `default` if the offset lies outside of the kernel.
+    pub fn at_or(&self, x: isize, y: isize, default: f64) -> f64 {
+        let radius = (self.size() / 2) as isize;
+
+        if x.abs() <= radius && y.abs() <= radius {
+            self.at(x, y)
+        } else {
+            default
+        }
+    }
+
     /// Rotate kernel matrix clockwise by `degrees`. Only multiples of 90° are supported.
-    pub fn rotate(&mut self, degrees: usize) -> Result<(), String> {
+    /// Arbitrary angles are supported by [`rotate_by()`](Kernel::rotate_by).
+    pub fn rotate(&mut self, degrees: usize) -> Result<(), KernelGeneratorError> {
         if degrees % 90 != 0 {
-            Err("degrees must be a multiple of 90.".into())
+            Err(KernelGeneratorError::InvalidParameter(
+                "degrees must be a multiple of 90".into(),
+            ))
         } else {
+            self.make_dense();
+
             let n = self.probabilities.len();
 
             for _ in 0..degrees / 90 {
@@ -135,10 +404,151 @@ impl Kernel {
                 }
             }
 
+            self.update_representation();
+
             Ok(())
         }
     }
 
+    /// Rotates the kernel clockwise by an arbitrary angle of `degrees`, i.e. in the same direction
+    /// as [`rotate()`](Kernel::rotate). The probabilities are resampled using the given
+    /// [`Interpolation`] and renormalized so that the sum of the kernel does not change.
+    ///
+    /// If `grow` is set, the kernel is enlarged so that no probability mass is clipped at the
+    /// corners. Otherwise, the size of the kernel is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `degrees` is not finite or if the kernel is not defined on a square
+    /// lattice.
+    pub fn rotate_by(
+        &mut self,
+        degrees: f64,
+        interpolation: Interpolation,
+        grow: bool,
+    ) -> Result<(), KernelGeneratorError> {
+        if !degrees.is_finite() {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "degrees must be finite".into(),
+            ));
+        }
+
+        if self.lattice == Lattice::Hexagonal {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "kernels can only be rotated by arbitrary angles on square lattices".into(),
+            ));
+        }
+
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let radius = (self.size() / 2) as isize;
+
+        // Half-width of the rotated kernel, with a tolerance for multiples of 90°
+        let new_radius = if grow {
+            ((radius as f64 + 0.5) * (sin.abs() + cos.abs()) - 0.5 - 1e-9).ceil() as isize
+        } else {
+            radius
+        }
+        .max(radius);
+        let size = 2 * new_radius as usize + 1;
+        let mut rotated = vec![vec![0.0; size]; size];
+        let index = |v: isize| (new_radius + v) as usize;
+
+        match interpolation {
+            Interpolation::Bilinear => {
+                for x in -new_radius..=new_radius {
+                    for y in -new_radius..=new_radius {
+                        // Rotate back to find the position in the original kernel
+                        let sx = x as f64 * cos + y as f64 * sin;
+                        let sy = -(x as f64) * sin + y as f64 * cos;
+
+                        let (x0, y0) = (sx.floor(), sy.floor());
+                        let (fx, fy) = (sx - x0, sy - y0);
+                        let (x0, y0) = (x0 as isize, y0 as isize);
+
+                        rotated[index(x)][index(y)] =
+                            (1.0 - fx) * (1.0 - fy) * self.at_or(x0, y0, 0.0)
+                                + fx * (1.0 - fy) * self.at_or(x0 + 1, y0, 0.0)
+                                + (1.0 - fx) * fy * self.at_or(x0, y0 + 1, 0.0)
+                                + fx * fy * self.at_or(x0 + 1, y0 + 1, 0.0);
+                    }
+                }
+            }
+            Interpolation::AreaWeighted => {
+                let n = ROTATION_SUBSAMPLES;
+
+                for x in -radius..=radius {
+                    for y in -radius..=radius {
+                        let mass = self.at(x, y) / (n * n) as f64;
+
+                        if mass == 0.0 {
+                            continue;
+                        }
+
+                        // Move each part of the field to the field it is rotated onto
+                        for i in 0..n {
+                            for j in 0..n {
+                                let sx = x as f64 + (i as f64 + 0.5) / n as f64 - 0.5;
+                                let sy = y as f64 + (j as f64 + 0.5) / n as f64 - 0.5;
+
+                                let tx = (sx * cos - sy * sin).round() as isize;
+                                let ty = (sx * sin + sy * cos).round() as isize;
+
+                                if tx.abs() <= new_radius && ty.abs() <= new_radius {
+                                    rotated[index(tx)][index(ty)] += mass;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Renormalize to the original sum
+        let sum = self.sum();
+        let rotated_sum: f64 = rotated.iter().flatten().sum();
+
+        if rotated_sum > 0.0 {
+            for probability in rotated.iter_mut().flatten() {
+                *probability *= sum / rotated_sum;
+            }
+        }
+
+        self.replace_probabilities(rotated);
+
+        Ok(())
+    }
+
+    /// Creates a heading-dependent kernel family from a prototype kernel heading north.
+    ///
+    /// The kernel of each sector is the prototype rotated by the [heading](HeadingSectors::heading)
+    /// of the sector. The last kernel is used after staying in place and is the mixture of all
+    /// rotated kernels. The kernels are ordered by the variants of the [`HeadingSectors`] and can
+    /// be used like the kernels of a
+    /// [`CorrelatedSectorsGenerator`](crate::kernel::correlated_sectors::CorrelatedSectorsGenerator).
+    pub fn rotations(
+        &self,
+        sectors: HeadingSectors,
+        interpolation: Interpolation,
+        grow: bool,
+    ) -> Result<Vec<Kernel>, KernelGeneratorError> {
+        let mut kernels = Vec::with_capacity(sectors.variants());
+
+        for sector in 0..sectors.sectors() {
+            let mut kernel = self.clone();
+
+            kernel.rotate_by(sectors.heading(sector), interpolation, grow)?;
+            kernels.push(kernel);
+        }
+
+        let components: Vec<_> = kernels.iter().map(|kernel| (1.0, kernel.clone())).collect();
+        let mut stay = Kernel::mixture(&components)?;
+        stay.name = self.name.clone();
+
+        kernels.push(stay);
+
+        Ok(kernels)
+    }
+
     pub fn name(&self, short: bool) -> String {
         if short {
             self.name.0.clone()
@@ -146,11 +556,197 @@ impl Kernel {
             self.name.1.clone()
         }
     }
+
+    /// Computes the two-dimensional convolution of two kernels of possibly different sizes.
+    ///
+    /// The resulting kernel contains the probabilities of making a step according to `self`
+    /// followed by a step according to `other`. Its size is the sum of both sizes minus one.
+    pub fn convolve(&self, other: &Kernel) -> Result<Kernel, KernelGeneratorError> {
+        if self.lattice != other.lattice {
+            return Err(KernelGeneratorError::LatticeMismatch);
+        }
+
+        let size = self.size() + other.size() - 1;
+        let mut kernel = Kernel {
+            probabilities: vec![vec![0.0; size]; size],
+            sparse: None,
+            name: (
+                format!("{}*{}", self.name.0, other.name.0),
+                format!("{} * {}", self.name.1, other.name.1),
+            ),
+            lattice: self.lattice,
+        };
+
+        let support = other.support();
+
+        for (x1, y1, p) in self.support() {
+            if p == 0.0 {
+                continue;
+            }
+
+            for &(x2, y2, q) in support.iter() {
+                let (x, y) = (x1 + x2, y1 + y2);
+
+                kernel.set(x, y, kernel.at(x, y) + p * q);
+            }
+        }
+
+        kernel.update_representation();
+
+        Ok(kernel)
+    }
+
+    /// Computes the `k`-th convolution power of the kernel, i.e. the kernel of `k` consecutive
+    /// steps. For `k = 0`, a kernel of size 1 that always stays is returned.
+    pub fn pow(&self, k: usize) -> Kernel {
+        let mut result = Kernel {
+            probabilities: vec![vec![1.0]],
+            sparse: None,
+            name: self.name.clone(),
+            lattice: self.lattice,
+        };
+        let mut base = self.clone();
+        let mut k = k;
+
+        // Exponentiation by squaring
+        while k > 0 {
+            if k % 2 == 1 {
+                result = result.convolve(&base).unwrap();
+            }
+
+            k /= 2;
+
+            if k > 0 {
+                base = base.convolve(&base).unwrap();
+            }
+        }
+
+        result.name = self.name.clone();
+
+        result
+    }
+
+    /// Creates a weighted mixture of kernels, e.g. 80% resting and 20% travelling.
+    ///
+    /// The weights are normalized so that they sum up to 1.0. Kernels of different sizes are
+    /// padded with zeros to the size of the largest kernel.
+    pub fn mixture(components: &[(f64, Kernel)]) -> Result<Kernel, KernelGeneratorError> {
+        let Some((_, first)) = components.first() else {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "a mixture requires at least one kernel".into(),
+            ));
+        };
+
+        if components
+            .iter()
+            .any(|(_, kernel)| kernel.lattice != first.lattice)
+        {
+            return Err(KernelGeneratorError::LatticeMismatch);
+        }
+
+        if components
+            .iter()
+            .any(|(weight, _)| !weight.is_finite() || *weight < 0.0)
+        {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "mixture weights must be finite and non-negative".into(),
+            ));
+        }
+
+        let total: f64 = components.iter().map(|(weight, _)| weight).sum();
+
+        if total == 0.0 {
+            return Err(KernelGeneratorError::InvalidParameter(
+                "mixture weights must not all be zero".into(),
+            ));
+        }
+
+        let size = components
+            .iter()
+            .map(|(_, kernel)| kernel.size())
+            .max()
+            .unwrap();
+        let mut kernel = Kernel {
+            probabilities: vec![vec![0.0; size]; size],
+            sparse: None,
+            name: ("mix".into(), "Mixture".into()),
+            lattice: first.lattice,
+        };
+
+        for (weight, component) in components {
+            for (x, y, p) in component.support() {
+                kernel.set(x, y, kernel.at(x, y) + weight / total * p);
+            }
+        }
+
+        kernel.update_representation();
+
+        Ok(kernel)
+    }
+
+    /// Elementwise addition that returns an error instead of panicking if the sizes differ.
+    pub fn try_add(&self, rhs: &Kernel) -> Result<Kernel, KernelGeneratorError> {
+        self.try_elementwise(rhs, |a, b| a + b)
+    }
+
+    /// Elementwise multiplication that returns an error instead of panicking if the sizes differ.
+    pub fn try_mul(&self, rhs: &Kernel) -> Result<Kernel, KernelGeneratorError> {
+        self.try_elementwise(rhs, |a, b| a * b)
+    }
+
+    /// Elementwise division that returns an error instead of panicking if the sizes differ.
+    pub fn try_div(&self, rhs: &Kernel) -> Result<Kernel, KernelGeneratorError> {
+        self.try_elementwise(rhs, |a, b| a / b)
+    }
+
+    fn try_elementwise(
+        &self,
+        rhs: &Kernel,
+        op: impl Fn(f64, f64) -> f64,
+    ) -> Result<Kernel, KernelGeneratorError> {
+        if self.size() != rhs.size() {
+            return Err(KernelGeneratorError::SizeMismatch(self.size(), rhs.size()));
+        }
+
+        let (lhs, rhs) = (self.to_dense(), rhs.to_dense());
+        let probabilities = lhs
+            .iter()
+            .zip(rhs.iter())
+            .map(|(a, b)| a.iter().zip(b.iter()).map(|(a, b)| op(*a, *b)).collect())
+            .collect();
+
+        let mut new_kernel = self.clone();
+        new_kernel.replace_probabilities(probabilities);
+
+        Ok(new_kernel)
+    }
+
+    /// Returns the [`Lattice`] the kernel is defined on.
+    pub fn lattice(&self) -> Lattice {
+        self.lattice
+    }
+
+    /// Sets the [`Lattice`] the kernel is defined on.
+    pub fn with_lattice(mut self, lattice: Lattice) -> Self {
+        self.lattice = lattice;
+
+        self
+    }
+
+    /// Replaces all probabilities of the kernel by a dense matrix indexed by `[x][y]` and chooses
+    /// the representation of the kernel.
+    fn replace_probabilities(&mut self, probabilities: Vec<Vec<f64>>) {
+        self.probabilities = probabilities;
+        self.sparse = None;
+
+        self.update_representation();
+    }
 }
 
 impl Debug for Kernel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut res = String::new();
+        let radius = (self.size() / 2) as isize;
 
         for y in 0..self.size() {
             for x in 0..self.size() {
@@ -158,10 +754,13 @@ impl Debug for Kernel {
                     res.remove(res.len() - 1);
                     res += "[";
                 }
-                if self.probabilities[x][y] == 0.0 {
+
+                let probability = self.at(x as isize - radius, y as isize - radius);
+
+                if probability == 0.0 {
                     res += "_._____";
                 } else {
-                    res += &format!("{:.5}", self.probabilities[x][y]);
+                    res += &format!("{:.5}", probability);
                 }
                 if x == self.size() / 2 && y == self.size() / 2 {
                     res += "]";
@@ -178,7 +777,12 @@ impl Debug for Kernel {
 
 impl PartialEq for Kernel {
     fn eq(&self, other: &Self) -> bool {
-        self.probabilities == other.probabilities
+        let probabilities_eq = match (&self.sparse, &other.sparse) {
+            (None, None) => self.probabilities == other.probabilities,
+            _ => self.size() == other.size() && self.to_sparse() == other.to_sparse(),
+        };
+
+        probabilities_eq && self.lattice == other.lattice
     }
 }
 
@@ -186,19 +790,8 @@ impl Add for Kernel {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        if self.size() == rhs.size() {
-            let mut new_kernel = self.clone();
-
-            for x in 0..self.size() {
-                for y in 0..self.size() {
-                    new_kernel.probabilities[x][y] += rhs.probabilities[x][y];
-                }
-            }
-
-            new_kernel
-        } else {
-            panic!("both kernels must have the same size for addition");
-        }
+        self.try_add(&rhs)
+            .expect("both kernels must have the same size for addition")
     }
 }
 
@@ -206,33 +799,16 @@ impl Mul for Kernel {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        if self.size() == rhs.size() {
-            let mut new_kernel = self.clone();
-
-            for x in 0..self.size() {
-                for y in 0..self.size() {
-                    new_kernel.probabilities[x][y] *= rhs.probabilities[x][y];
-                }
-            }
-
-            new_kernel
-        } else {
-            panic!("both kernels must have the same size for multiplication");
-        }
+        self.try_mul(&rhs)
+            .expect("both kernels must have the same size for multiplication")
     }
 }
 
 impl MulAssign for Kernel {
     fn mul_assign(&mut self, rhs: Self) {
-        if self.size() == rhs.size() {
-            for x in 0..self.size() {
-                for y in 0..self.size() {
-                    self.probabilities[x][y] *= rhs.probabilities[x][y];
-                }
-            }
-        } else {
-            panic!("both kernels must have the same size for multiplication");
-        }
+        *self = self
+            .try_mul(&rhs)
+            .expect("both kernels must have the same size for multiplication");
     }
 }
 
@@ -240,33 +816,16 @@ impl Div for Kernel {
     type Output = Self;
 
     fn div(self, rhs: Self) -> Self::Output {
-        if self.size() == rhs.size() {
-            let mut new_kernel = self.clone();
-
-            for x in 0..self.size() {
-                for y in 0..self.size() {
-                    new_kernel.probabilities[x][y] /= rhs.probabilities[x][y];
-                }
-            }
-
-            new_kernel
-        } else {
-            panic!("both kernels must have the same size for division");
-        }
+        self.try_div(&rhs)
+            .expect("both kernels must have the same size for division")
     }
 }
 
 impl DivAssign for Kernel {
     fn div_assign(&mut self, rhs: Self) {
-        if self.size() == rhs.size() {
-            for x in 0..self.size() {
-                for y in 0..self.size() {
-                    self.probabilities[x][y] /= rhs.probabilities[x][y];
-                }
-            }
-        } else {
-            panic!("both kernels must have the same size for division");
-        }
+        *self = self
+            .try_div(&rhs)
+            .expect("both kernels must have the same size for division");
     }
 }
 
@@ -282,10 +841,15 @@ macro_rules! kernel {
         }
 
         let mut kernel = Kernel::try_new(size, ("ck".into(), "Custom Kernel".into())).unwrap();
+        let radius = (size / 2) as isize;
 
-        kernel.probabilities = (0..size)
-            .map(|y| probs.iter().skip(y).step_by(size).copied().collect())
-            .collect();
+        for (i, probability) in probs.iter().enumerate() {
+            let (x, y) = ((i % size) as isize - radius, (i / size) as isize - radius);
+
+            kernel.set(x, y, *probability);
+        }
+
+        kernel.update_representation();
 
         kernel
     }}
@@ -378,7 +942,13 @@ impl<T> IndexMut<Direction> for Directions<T> {
 #[cfg(test)]
 #[rustfmt::skip]
 mod tests {
-    use crate::kernel::Kernel;
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::DynamicPrograms;
+    use crate::kernel::generator::KernelGeneratorError;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::correlated_sectors::HeadingSectors;
+    use crate::kernel::{Interpolation, Kernel, KernelValidationError};
+    use crate::lattice::Lattice;
 
     #[test]
     fn test_rotate_invalid() {
@@ -459,9 +1029,226 @@ mod tests {
                 vec![2.0, 5.0, 8.0],
                 vec![3.0, 6.0, 9.0],
             ],
+            sparse: None,
             name: ("".into(), "".into()),
+            lattice: Lattice::Square,
         };
 
         assert_eq!(kernel, kernel_correct);
     }
+
+    #[test]
+    fn test_convolve() {
+        let srw = Kernel::from_generator(SimpleRwGenerator).unwrap();
+        let two_steps = srw.convolve(&srw).unwrap();
+
+        assert_eq!(two_steps.size(), 5);
+        assert!((two_steps.sum() - 1.0).abs() < 1e-12);
+        assert!((two_steps.at(0, 0) - 0.2).abs() < 1e-12);
+        assert!((two_steps.at(1, 0) - 0.08).abs() < 1e-12);
+        assert!((two_steps.at(2, 0) - 0.04).abs() < 1e-12);
+        assert!((two_steps.at(1, 1) - 0.08).abs() < 1e-12);
+        assert_eq!(two_steps.at(2, 1), 0.0);
+
+        // Kernels of different sizes
+        let large = Kernel::try_from_value(5, 1.0 / 25.0).unwrap();
+        let convolved = srw.convolve(&large).unwrap();
+
+        assert_eq!(convolved.size(), 7);
+        assert!((convolved.sum() - 1.0).abs() < 1e-12);
+        assert!((convolved.at(3, 0) - 0.2 / 25.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_pow() {
+        let srw = Kernel::from_generator(SimpleRwGenerator).unwrap();
+
+        assert_eq!(srw.pow(0).size(), 1);
+        assert_eq!(srw.pow(0).at(0, 0), 1.0);
+        assert_eq!(srw.pow(1), srw);
+
+        let expected = srw.convolve(&srw).unwrap().convolve(&srw).unwrap();
+        let three_steps = srw.pow(3);
+
+        assert_eq!(three_steps.size(), 7);
+
+        for x in -3..=3 {
+            for y in -3..=3 {
+                assert!((three_steps.at(x, y) - expected.at(x, y)).abs() < 1e-15);
+            }
+        }
+
+        // One step with the power kernel equals multiple steps with the original kernel
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(srw.clone())
+            .build()
+            .unwrap();
+        let mut dp_pow = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(srw.pow(3))
+            .build()
+            .unwrap();
+
+        dp.compute();
+        dp_pow.compute();
+
+        for x in -3..=3 {
+            for y in -3..=3 {
+                let (a, b) = (dp.at(x, y, 6, 0).unwrap(), dp_pow.at(x, y, 2, 0).unwrap());
+
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_operators() {
+        let small = Kernel::try_from_value(3, 2.0).unwrap();
+        let large = Kernel::try_from_value(5, 2.0).unwrap();
+
+        assert!(matches!(
+            small.try_add(&large),
+            Err(KernelGeneratorError::SizeMismatch(3, 5))
+        ));
+        assert!(small.try_mul(&large).is_err());
+        assert!(small.try_div(&large).is_err());
+
+        assert_eq!(small.try_add(&small).unwrap(), Kernel::try_from_value(3, 4.0).unwrap());
+        assert_eq!(small.try_mul(&small).unwrap(), Kernel::try_from_value(3, 4.0).unwrap());
+        assert_eq!(small.try_div(&small).unwrap(), Kernel::try_from_value(3, 1.0).unwrap());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Kernel::from_generator(SimpleRwGenerator)
+            .unwrap()
+            .validate()
+            .is_ok());
+
+        let kernel = Kernel::try_from_value(3, 0.5).unwrap();
+
+        assert_eq!(
+            kernel.validate(),
+            Err(KernelValidationError::NotNormalized { sum: 4.5 })
+        );
+
+        let mut kernel = Kernel::try_from_value(3, 0.25).unwrap();
+        kernel.set(1, 0, -0.25);
+        kernel.set(0, -1, f64::INFINITY);
+
+        assert!(matches!(
+            kernel.validate(),
+            Err(KernelValidationError::InvalidProbabilities { cells, .. })
+                if cells == vec![(0, -1, f64::INFINITY), (1, 0, -0.25)]
+        ));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut kernel = Kernel::try_from_value(3, 2.0).unwrap();
+
+        kernel.normalize().unwrap();
+
+        assert!(kernel.validate().is_ok());
+        assert!((kernel.at(1, 1) - 1.0 / 9.0).abs() < 1e-15);
+
+        let mut kernel = Kernel::try_from_value(3, 0.0).unwrap();
+
+        assert_eq!(kernel.normalize(), Err(KernelValidationError::ZeroSum));
+
+        let mut kernel = Kernel::try_from_value(3, 1.0).unwrap();
+        kernel.set(0, 0, -1.0);
+
+        assert!(kernel.normalize().is_err());
+        assert_eq!(kernel.at(0, 0), -1.0);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_rotate_by_right_angles() {
+        let kernel = kernel![
+            1.0, 2.0, 3.0,
+            4.0, 5.0, 6.0,
+            7.0, 8.0, 9.0,
+        ];
+
+        for degrees in [90, 180, 270] {
+            let mut expected = kernel.clone();
+            expected.rotate(degrees).unwrap();
+
+            for interpolation in [Interpolation::Bilinear, Interpolation::AreaWeighted] {
+                let mut rotated = kernel.clone();
+                rotated.rotate_by(degrees as f64, interpolation, true).unwrap();
+
+                assert_eq!(rotated.size(), 3);
+
+                for x in -1..=1 {
+                    for y in -1..=1 {
+                        assert!((rotated.at(x, y) - expected.at(x, y)).abs() < 1e-9);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotate_by() {
+        let mut kernel = Kernel::try_from_value(5, 0.0).unwrap();
+        kernel.set(0, -2, 0.5);
+        kernel.set(0, 0, 0.5);
+
+        // Rotating north by 45° clockwise moves the mass to the north-east
+        for interpolation in [Interpolation::Bilinear, Interpolation::AreaWeighted] {
+            let mut rotated = kernel.clone();
+            rotated.rotate_by(45.0, interpolation, false).unwrap();
+
+            assert_eq!(rotated.size(), 5);
+            assert!((rotated.sum() - 1.0).abs() < 1e-12);
+            assert!(rotated.at(1, -1) > rotated.at(-1, -1));
+            assert!(rotated.at(1, -1) > rotated.at(0, -2));
+            assert!(rotated.at(1, -1) > rotated.at(-1, 1));
+        }
+
+        // Growing keeps the mass of the corners in place instead of renormalizing the rest
+        let mut kernel = Kernel::try_from_value(5, 0.0).unwrap();
+        kernel.set(2, 2, 1.0);
+
+        let mut clipped = kernel.clone();
+        clipped.rotate_by(45.0, Interpolation::AreaWeighted, false).unwrap();
+        let mut grown = kernel.clone();
+        grown.rotate_by(45.0, Interpolation::AreaWeighted, true).unwrap();
+
+        assert_eq!(clipped.size(), 5);
+        assert!((clipped.sum() - 1.0).abs() < 1e-12);
+        assert_eq!(grown.size(), 9);
+        assert!((grown.sum() - 1.0).abs() < 1e-12);
+        assert!(grown.at(0, 3) > 0.5);
+        assert!(grown.at(0, 3) > grown.at(0, 2));
+
+        assert!(kernel
+            .rotate_by(f64::NAN, Interpolation::Bilinear, false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rotations() {
+        let mut kernel = Kernel::try_from_value(3, 0.0).unwrap();
+        kernel.set(0, -1, 1.0);
+
+        let kernels = kernel
+            .rotations(HeadingSectors::new(4), Interpolation::Bilinear, false)
+            .unwrap();
+
+        assert_eq!(kernels.len(), 5);
+
+        for (kernel, (x, y)) in kernels.iter().zip([(0, -1), (1, 0), (0, 1), (-1, 0)]) {
+            assert!((kernel.at(x, y) - 1.0).abs() < 1e-9);
+        }
+
+        assert!((kernels[4].at(1, 0) - 0.25).abs() < 1e-9);
+        assert!((kernels[4].sum() - 1.0).abs() < 1e-9);
+    }
 }
@@ -36,8 +36,8 @@ impl KernelGenerator for NormalDistGenerator {
             .ok_or(KernelGeneratorError::OneKernelRequired)?;
 
         let mean = vec![
-            (self.size / 2 + self.mean.x as usize) as f64,
-            (self.size / 2 + self.mean.y as usize) as f64,
+            (self.size / 2) as f64 + self.mean.x as f64,
+            (self.size / 2) as f64 + self.mean.y as f64,
         ];
         let cov = vec![self.diffusion, 0.0, 0.0, self.diffusion];
         let distribution = MultivariateNormal::new(mean, cov).unwrap();
@@ -68,3 +68,29 @@ impl KernelGenerator for NormalDistGenerator {
         ("nd".into(), "Normal Distribution".into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::point::XYPoint;
+    use crate::kernel::normal_dist::NormalDistGenerator;
+    use crate::kernel::Kernel;
+    use crate::xy;
+
+    #[test]
+    fn test_normal_dist_negative_mean() {
+        let kernel = Kernel::from_generator(NormalDistGenerator::new(1.0, 7, xy!(-2, 1))).unwrap();
+        let radius = 3;
+        let (mut max, mut max_at) = (0.0, (0, 0));
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                if kernel.at(x, y) > max {
+                    (max, max_at) = (kernel.at(x, y), (x, y));
+                }
+            }
+        }
+
+        assert!((kernel.sum() - 1.0).abs() < 1e-12);
+        assert_eq!(max_at, (-2, 1));
+    }
+}
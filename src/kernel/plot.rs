@@ -0,0 +1,208 @@
+//! Provides plotting of kernels as heatmap images.
+//!
+//! [`Kernel::plot()`] renders a single kernel and [`Kernel::plot_multiple()`] renders a family of
+//! kernels, e.g. the kernels of a correlated random walk, as a grid titled by
+//! [`Kernel::name()`]. Probabilities are colored using the viridis color map next to a color scale,
+//! north is at the top and the center of each kernel is outlined in red.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use randomwalks_lib::dataset::point::XYPoint;
+//! use randomwalks_lib::kernel::correlated_rw::CorrelatedRwGenerator;
+//! use randomwalks_lib::kernel::normal_dist::NormalDistGenerator;
+//! use randomwalks_lib::kernel::Kernel;
+//! use randomwalks_lib::xy;
+//!
+//! let kernel = Kernel::from_generator(NormalDistGenerator {
+//!     diffusion: 3.0,
+//!     size: 21,
+//!     mean: xy!(0, 0),
+//! })
+//! .unwrap();
+//! let kernels = Kernel::multiple_from_generator(CorrelatedRwGenerator {
+//!     persistence: 0.5,
+//! })
+//! .unwrap();
+//!
+//! kernel.plot("kernel.png").unwrap();
+//! Kernel::plot_multiple(&kernels, "kernels.png").unwrap();
+//! ```
+
+use anyhow::bail;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::kernel::Kernel;
+
+/// Width and height in pixels of a single kernel.
+const TILE_SIZE: u32 = 500;
+
+/// Width in pixels of the color scale.
+const SCALE_WIDTH: u32 = 150;
+
+impl Kernel {
+    /// Plots the kernel as a heatmap and saves the resulting image to a .png file.
+    pub fn plot<S: Into<String>>(&self, filename: S) -> anyhow::Result<()> {
+        Kernel::plot_multiple(std::slice::from_ref(self), filename)
+    }
+
+    /// Plots multiple kernels as a grid of heatmaps sharing one color scale and saves the
+    /// resulting image to a .png file.
+    pub fn plot_multiple<S: Into<String>>(kernels: &[Kernel], filename: S) -> anyhow::Result<()> {
+        if kernels.is_empty() {
+            bail!("Cannot plot empty list of kernels");
+        }
+
+        let filename = filename.into();
+
+        // Arrange kernels in a roughly square grid
+
+        let columns = (kernels.len() as f64).sqrt().ceil() as usize;
+        let rows = kernels.len().div_ceil(columns);
+
+        let root = BitMapBackend::new(
+            &filename,
+            (
+                columns as u32 * TILE_SIZE + SCALE_WIDTH,
+                rows as u32 * TILE_SIZE,
+            ),
+        )
+        .into_drawing_area();
+        root.fill(&WHITE)?;
+
+        let (grid, scale) = root.split_horizontally(columns as u32 * TILE_SIZE);
+
+        let max = kernels
+            .iter()
+            .flat_map(|kernel| kernel.support())
+            .map(|(_, _, probability)| probability)
+            .fold(0.0, f64::max);
+        let max = if max > 0.0 { max } else { 1.0 };
+
+        for (area, kernel) in grid.split_evenly((rows, columns)).iter().zip(kernels) {
+            draw_kernel(area, kernel, max)?;
+        }
+
+        draw_scale(&scale, max)?;
+
+        root.present()?;
+
+        Ok(())
+    }
+}
+
+/// Draws a kernel as a heatmap with probabilities colored from `0.0` to `max`.
+fn draw_kernel(
+    area: &DrawingArea<BitMapBackend, Shift>,
+    kernel: &Kernel,
+    max: f64,
+) -> anyhow::Result<()> {
+    let radius = (kernel.size() / 2) as isize;
+    let range = radius as f64 + 0.5;
+
+    // Labels are placed at the centers of the fields
+    let labels = kernel.size().min(11);
+
+    let mut chart = ChartBuilder::on(&area.margin(10, 10, 10, 10))
+        .caption(kernel.name(false), ("sans-serif", 20))
+        .x_label_area_size(20)
+        .y_label_area_size(30)
+        .build_cartesian_2d(-range..range, range..-range)?;
+
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .x_labels(labels)
+        .y_labels(labels)
+        .x_label_formatter(&|x| format!("{:.0}", x))
+        .y_label_formatter(&|y| format!("{:.0}", y))
+        .draw()?;
+
+    let mut cells = Vec::new();
+
+    for x in -radius..=radius {
+        for y in -radius..=radius {
+            let (x_f, y_f) = (x as f64, y as f64);
+            let color = ViridisRGB::get_color_normalized(kernel.at(x, y), 0.0, max);
+
+            cells.push(Rectangle::new(
+                [(x_f - 0.5, y_f - 0.5), (x_f + 0.5, y_f + 0.5)],
+                color.filled(),
+            ));
+        }
+    }
+
+    chart.draw_series(cells)?;
+
+    // Mark the center of the kernel
+
+    chart.draw_series([Rectangle::new(
+        [(-0.5, -0.5), (0.5, 0.5)],
+        RED.stroke_width(2),
+    )])?;
+
+    Ok(())
+}
+
+/// Draws a vertical color scale from `0.0` to `max`.
+fn draw_scale(area: &DrawingArea<BitMapBackend, Shift>, max: f64) -> anyhow::Result<()> {
+    let steps = 100;
+
+    let mut chart = ChartBuilder::on(&area.margin(40, 40, 10, 60))
+        .y_label_area_size(60)
+        .build_cartesian_2d(0.0..1.0, 0.0..max)?;
+
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .disable_x_axis()
+        .y_label_style(("sans-serif", 12))
+        .y_label_formatter(&|p| format!("{:.3}", p))
+        .draw()?;
+
+    chart.draw_series((0..steps).map(|i| {
+        let (low, high) = (
+            max * i as f64 / steps as f64,
+            max * (i + 1) as f64 / steps as f64,
+        );
+
+        Rectangle::new(
+            [(0.0, low), (1.0, high)],
+            ViridisRGB::get_color_normalized(low, 0.0, max).filled(),
+        )
+    }))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::correlated_rw::CorrelatedRwGenerator;
+    use crate::kernel::Kernel;
+
+    #[test]
+    fn test_plot_kernels() {
+        let kernels =
+            Kernel::multiple_from_generator(CorrelatedRwGenerator { persistence: 0.5 }).unwrap();
+        let path = std::env::temp_dir().join("randomwalks_test_plot_kernels.png");
+
+        Kernel::plot_multiple(&kernels, path.to_string_lossy()).unwrap();
+
+        let (width, height) = image_size(&std::fs::read(&path).unwrap());
+
+        // 5 kernels are arranged in 3 columns and 2 rows
+        assert_eq!((width, height), (3 * 500 + 150, 2 * 500));
+        assert!(Kernel::plot_multiple(&[], path.to_string_lossy()).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Reads the width and height from the header of a .png file.
+    fn image_size(png: &[u8]) -> (u32, u32) {
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+
+        (width, height)
+    }
+}
@@ -0,0 +1,383 @@
+//! Provides a sparse representation for kernels that are mostly zero.
+//!
+//! Multi-step and jump kernels, e.g. a ring of fields at a fixed distance, are large but contain
+//! only few non-zero probabilities. A [`SparseKernel`] stores just the non-zero entries as a list
+//! of offsets and probabilities.
+//!
+//! The representation is chosen automatically whenever a kernel is created or transformed: if the
+//! [density](Kernel::density) of a kernel, i.e. the fraction of non-zero fields, falls below
+//! [`SPARSE_DENSITY_THRESHOLD`], the kernel only stores its non-zero entries and its
+//! [`support()`](Kernel::support) only contains those. Otherwise, the kernel is stored as a dense
+//! matrix and its support contains all fields of the kernel. Dynamic programs, walkers sampling using
+//! [`DynamicProgram::transition_probability()`](crate::dp::simple::DynamicProgram::transition_probability)
+//! and the kernel algebra, e.g. [`Kernel::convolve()`], iterate over the support, so sparse
+//! kernels are handled transparently.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::kernel::sparse::KernelRepresentation;
+//! use randomwalks_lib::kernel::Kernel;
+//!
+//! let jumps = vec![(5, 0, 0.25), (-5, 0, 0.25), (0, 5, 0.25), (0, -5, 0.25)];
+//! let kernel = Kernel::from_entries(5, &jumps).unwrap();
+//!
+//! assert_eq!(kernel.size(), 11);
+//! assert_eq!(kernel.representation(), KernelRepresentation::Sparse);
+//! assert_eq!(kernel.support().len(), 4);
+//! assert_eq!(kernel.to_sparse().at(0, -5), 0.25);
+//! ```
+
+use anyhow::bail;
+
+use crate::kernel::Kernel;
+use crate::lattice::Lattice;
+
+/// Density below which kernels are represented sparsely.
+pub const SPARSE_DENSITY_THRESHOLD: f64 = 0.25;
+
+/// The representation used when iterating over the entries of a kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelRepresentation {
+    /// All fields of the kernel are used.
+    Dense,
+
+    /// Only the non-zero fields of the kernel are used.
+    Sparse,
+}
+
+/// A kernel stored as a list of non-zero entries `(x, y, probability)` sorted by their offsets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseKernel {
+    radius: isize,
+    entries: Vec<(isize, isize, f64)>,
+}
+
+impl SparseKernel {
+    /// Returns the radius of the dense kernel the entries belong to.
+    pub fn radius(&self) -> isize {
+        self.radius
+    }
+
+    /// Returns all non-zero entries as `(x, y, probability)`.
+    pub fn entries(&self) -> &[(isize, isize, f64)] {
+        &self.entries
+    }
+
+    /// Returns the number of non-zero entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the kernel has no non-zero entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the probability at `(x, y)`, which is `0.0` for all offsets without an entry.
+    pub fn at(&self, x: isize, y: isize) -> f64 {
+        match self.find(x, y) {
+            Ok(i) => self.entries[i].2,
+            Err(_) => 0.0,
+        }
+    }
+
+    /// Sets the probability at `(x, y)`. Entries set to zero are removed.
+    pub(crate) fn set(&mut self, x: isize, y: isize, probability: f64) {
+        assert!(
+            x.abs() <= self.radius && y.abs() <= self.radius,
+            "offset ({x}, {y}) lies outside of a kernel with radius {}",
+            self.radius
+        );
+
+        match self.find(x, y) {
+            Ok(i) if probability == 0.0 => {
+                self.entries.remove(i);
+            }
+            Ok(i) => self.entries[i].2 = probability,
+            Err(_) if probability == 0.0 => {}
+            Err(i) => self.entries.insert(i, (x, y, probability)),
+        }
+    }
+
+    /// Multiplies all entries by `factor`.
+    pub(crate) fn scale(&mut self, factor: f64) {
+        for (_, _, probability) in self.entries.iter_mut() {
+            *probability *= factor;
+        }
+    }
+
+    fn find(&self, x: isize, y: isize) -> Result<usize, usize> {
+        self.entries
+            .binary_search_by(|(ex, ey, _)| (*ex, *ey).cmp(&(x, y)))
+    }
+}
+
+impl Kernel {
+    /// Creates a kernel of the given radius from a list of entries `(x, y, probability)`. All
+    /// other fields are zero. Entries with the same offset are added up.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry lies outside of the kernel.
+    pub fn from_entries(radius: usize, entries: &[(isize, isize, f64)]) -> anyhow::Result<Kernel> {
+        let mut sparse = SparseKernel {
+            radius: radius as isize,
+            entries: Vec::new(),
+        };
+
+        for &(x, y, probability) in entries {
+            if x.abs() > sparse.radius || y.abs() > sparse.radius {
+                bail!("entry ({x}, {y}) lies outside of a kernel with radius {radius}");
+            }
+
+            sparse.set(x, y, sparse.at(x, y) + probability);
+        }
+
+        let mut kernel = Kernel {
+            probabilities: Vec::new(),
+            sparse: Some(sparse),
+            name: (String::new(), String::new()),
+            lattice: Lattice::default(),
+        };
+
+        kernel.update_representation();
+
+        Ok(kernel)
+    }
+
+    /// Returns the fraction of fields of the kernel that are non-zero.
+    pub fn density(&self) -> f64 {
+        let nonzero = match &self.sparse {
+            Some(sparse) => sparse.len(),
+            None => self
+                .probabilities
+                .iter()
+                .flatten()
+                .filter(|p| **p != 0.0)
+                .count(),
+        };
+
+        nonzero as f64 / (self.size() * self.size()).max(1) as f64
+    }
+
+    /// Returns the [`KernelRepresentation`] the kernel is currently stored in.
+    pub fn representation(&self) -> KernelRepresentation {
+        match self.sparse {
+            Some(_) => KernelRepresentation::Sparse,
+            None => KernelRepresentation::Dense,
+        }
+    }
+
+    /// Chooses the [`KernelRepresentation`] of the kernel by its [density](Kernel::density).
+    ///
+    /// This is done automatically whenever a kernel is created or transformed, but not by
+    /// [`set()`](Kernel::set), so call it after setting many probabilities by hand.
+    pub fn update_representation(&mut self) {
+        if self.size() == 0 {
+            return;
+        }
+
+        let sparse = self.density() < SPARSE_DENSITY_THRESHOLD;
+
+        match (sparse, self.sparse.is_some()) {
+            (true, false) => {
+                self.sparse = Some(self.to_sparse());
+                self.probabilities = Vec::new();
+            }
+            (false, true) => self.make_dense(),
+            _ => {}
+        }
+    }
+
+    /// Stores the kernel as a dense matrix, e.g. before modifying it in place.
+    pub(crate) fn make_dense(&mut self) {
+        if self.sparse.is_some() {
+            self.probabilities = self.to_dense();
+            self.sparse = None;
+        }
+    }
+
+    /// Returns the entries `(x, y, probability)` of the kernel that have to be considered, i.e.
+    /// only the non-zero entries for sparse kernels and all entries for dense kernels.
+    pub fn support(&self) -> Vec<(isize, isize, f64)> {
+        if let Some(sparse) = &self.sparse {
+            return sparse.entries.clone();
+        }
+
+        let radius = (self.size() / 2) as isize;
+        let mut support = Vec::with_capacity(self.size() * self.size());
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                support.push((x, y, self.at(x, y)));
+            }
+        }
+
+        support
+    }
+
+    /// Converts the kernel into a [`SparseKernel`] containing its non-zero entries.
+    pub fn to_sparse(&self) -> SparseKernel {
+        if let Some(sparse) = &self.sparse {
+            return sparse.clone();
+        }
+
+        let radius = (self.size() / 2) as isize;
+        let mut entries = Vec::new();
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                let probability = self.at(x, y);
+
+                if probability != 0.0 {
+                    entries.push((x, y, probability));
+                }
+            }
+        }
+
+        SparseKernel { radius, entries }
+    }
+
+    /// Returns all probabilities of the kernel as a dense matrix indexed by `[x][y]`.
+    pub fn to_dense(&self) -> Vec<Vec<f64>> {
+        let Some(sparse) = &self.sparse else {
+            return self.probabilities.clone();
+        };
+
+        let size = self.size();
+        let mut probabilities = vec![vec![0.0; size]; size];
+
+        for &(x, y, probability) in sparse.entries() {
+            probabilities[(x + sparse.radius) as usize][(y + sparse.radius) as usize] = probability;
+        }
+
+        probabilities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::sparse::KernelRepresentation;
+    use crate::kernel::Kernel;
+    use crate::walker::backtracking::BacktrackingWalker;
+    use crate::walker::Walker;
+
+    fn jumps() -> Kernel {
+        Kernel::from_entries(
+            3,
+            &[(3, 0, 0.25), (-3, 0, 0.25), (0, 3, 0.25), (0, -3, 0.25)],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sparse_representation() {
+        let kernel = jumps();
+        let sparse = kernel.to_sparse();
+
+        assert_eq!(kernel.size(), 7);
+        assert!((kernel.density() - 4.0 / 49.0).abs() < 1e-12);
+        assert_eq!(kernel.representation(), KernelRepresentation::Sparse);
+        assert_eq!(kernel.support().len(), 4);
+        assert_eq!(sparse.len(), 4);
+        assert_eq!(sparse.at(-3, 0), 0.25);
+        assert_eq!(sparse.at(1, 0), 0.0);
+
+        let dense = Kernel::from_generator(SimpleRwGenerator).unwrap();
+
+        assert_eq!(dense.representation(), KernelRepresentation::Dense);
+        assert_eq!(dense.support().len(), 9);
+        assert_eq!(dense.to_sparse().len(), 5);
+
+        assert!(Kernel::from_entries(1, &[(2, 0, 1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_sparse_storage() {
+        let mut kernel = jumps();
+
+        assert_eq!(kernel.to_dense()[6][3], 0.25);
+        assert_eq!(kernel.to_dense()[3][3], 0.0);
+        assert_eq!(kernel.probabilities(), kernel.to_dense());
+
+        // Setting probabilities keeps the representation until it is updated
+        for x in -3..=3 {
+            kernel.set(x, 1, 0.1);
+            kernel.set(x, -1, 0.1);
+        }
+
+        assert_eq!(kernel.representation(), KernelRepresentation::Sparse);
+        assert_eq!(kernel.at(-2, 1), 0.1);
+        assert_eq!(kernel.support().len(), 18);
+
+        kernel.update_representation();
+
+        assert_eq!(kernel.representation(), KernelRepresentation::Dense);
+        assert_eq!(kernel.support().len(), 49);
+
+        // Serialized kernels always use a dense matrix, so both representations are equal
+        let json = serde_json::to_string(&jumps()).unwrap();
+        let kernel: Kernel = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(kernel.representation(), KernelRepresentation::Sparse);
+        assert_eq!(kernel, jumps());
+        assert!(json.contains("probabilities"));
+
+        let mut rotated = jumps();
+        rotated.set(3, 0, 0.5);
+        rotated.rotate(90).unwrap();
+
+        assert_eq!(rotated.representation(), KernelRepresentation::Sparse);
+        assert_eq!(rotated.at(0, 3), 0.5);
+    }
+
+    #[test]
+    fn test_sparse_convolve() {
+        let kernel = jumps();
+        let squared = kernel.convolve(&kernel).unwrap();
+
+        assert_eq!(squared.size(), 13);
+        assert_eq!(squared.at(0, 0), 0.25);
+        assert_eq!(squared.at(6, 0), 1.0 / 16.0);
+        assert_eq!(squared.at(3, 3), 2.0 / 16.0);
+        assert!((squared.sum() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sparse_dp() {
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(9)
+            .kernel(jumps())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let walks = BacktrackingWalker
+            .generate_paths_seeded(&dp, 10, 3, 3, 6, 42)
+            .unwrap();
+
+        let DynamicProgramPool::Single(dp) = dp else {
+            unreachable!();
+        };
+
+        assert_eq!(dp.at(3, 0, 1), 0.25);
+        assert_eq!(dp.at(0, 0, 2), 0.25);
+        assert_eq!(dp.at(1, 0, 2), 0.0);
+        assert_eq!(dp.predecessors(0, 0).count(), 4);
+
+        for walk in walks.iter() {
+            for step in walk.0.windows(2) {
+                let (dx, dy) = (step[1].x - step[0].x, step[1].y - step[0].y);
+
+                assert_eq!(dx.abs() + dy.abs(), 3);
+                assert!(dx == 0 || dy == 0);
+            }
+        }
+    }
+}
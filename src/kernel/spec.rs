@@ -0,0 +1,534 @@
+//! Provides serializable specifications of kernel generators.
+//!
+//! A [`KernelGeneratorSpec`] describes one of the pre-defined kernel generators together with its
+//! parameters. Specs implement [`KernelGenerator`] themselves, so they can be passed directly to
+//! [`Kernel::from_generator()`] or [`Kernel::multiple_from_generator()`]. Every generator is
+//! identified by the unique short name returned by [`KernelGenerator::name()`]:
+//!
+//! | Name   | Generator                                                                               |
+//! |--------|-----------------------------------------------------------------------------------------|
+//! | `srw`  | [`SimpleRwGenerator`]                                                                   |
+//! | `brw`  | [`BiasedRwGenerator`]                                                                   |
+//! | `crw`  | [`CorrelatedRwGenerator`]                                                               |
+//! | `bcrw` | [`BiasedCorrelatedRwGenerator`]                                                         |
+//! | `nd`   | [`NormalDistGenerator`]                                                                 |
+//! | `hnd`  | [`HalfNormalDistGenerator`]                                                             |
+//! | `and`  | [`AnisotropicNormalDistGenerator`]                                                      |
+//! | `lrw`  | [`LatticeRwGenerator`]                                                                  |
+//! | `lw`   | [`LevyWalkGenerator`]                                                                   |
+//! | `dir`  | [`DirectionalGenerator`]                                                                |
+//! | `csrw` | [`CorrelatedSectorsGenerator`]                                                          |
+//! | `mix`  | [`MixtureGenerator`]                                                                    |
+//!
+//! Specs are serialized as objects with the name in the `generator` field and the parameters of
+//! the generator as further fields. They can also be parsed from strings of the form
+//! `name(parameter=value, ...)`, where each value is given in JSON. Parameters without a value
+//! can be omitted together with the parentheses.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::kernel::spec::KernelGeneratorSpec;
+//! use randomwalks_lib::kernel::Kernel;
+//!
+//! let spec: KernelGeneratorSpec = "brw(probability=0.5, direction=\"North\")".parse().unwrap();
+//! let kernel = Kernel::from_generator(&spec).unwrap();
+//!
+//! assert_eq!(kernel.name(true), "brw");
+//! assert_eq!(spec.to_string().parse::<KernelGeneratorSpec>().unwrap(), spec);
+//!
+//! let spec: KernelGeneratorSpec =
+//!     serde_json::from_str(r#"{ "generator": "crw", "persistence": 0.5 }"#).unwrap();
+//! let kernels = Kernel::multiple_from_generator(&spec).unwrap();
+//!
+//! assert_eq!(kernels.len(), 5);
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::dataset::point::XYPoint;
+use crate::kernel::anisotropic_normal_dist::AnisotropicNormalDistGenerator;
+use crate::kernel::biased_correlated_rw::BiasedCorrelatedRwGenerator;
+use crate::kernel::biased_rw::BiasedRwGenerator;
+use crate::kernel::correlated_rw::CorrelatedRwGenerator;
+use crate::kernel::correlated_sectors::{CorrelatedSectorsGenerator, HeadingSectors};
+use crate::kernel::directional::{
+    DirectionalGenerator, HeadingDistribution, StepLengthDistribution,
+};
+use crate::kernel::generator::{KernelGenerator, KernelGeneratorError};
+use crate::kernel::half_normal_dist::{HalfNormalDistGenerator, HalfNormalDistSide};
+use crate::kernel::lattice_rw::LatticeRwGenerator;
+use crate::kernel::levy_walk::LevyWalkGenerator;
+use crate::kernel::mixture::MixtureGenerator;
+use crate::kernel::normal_dist::NormalDistGenerator;
+use crate::kernel::simple_rw::SimpleRwGenerator;
+use crate::kernel::Direction;
+use crate::kernel::Kernel;
+use crate::lattice::Lattice;
+
+/// A kernel generator together with its parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "generator")]
+pub enum KernelGeneratorSpec {
+    #[serde(rename = "srw")]
+    SimpleRw,
+    #[serde(rename = "brw")]
+    BiasedRw {
+        probability: f64,
+        direction: Direction,
+    },
+    #[serde(rename = "crw")]
+    CorrelatedRw { persistence: f64 },
+    #[serde(rename = "bcrw")]
+    BiasedCorrelatedRw {
+        probability: f64,
+        direction: Direction,
+        persistence: f64,
+    },
+    #[serde(rename = "nd")]
+    NormalDist {
+        diffusion: f64,
+        size: usize,
+        #[serde(default)]
+        mean: XYPoint,
+    },
+    #[serde(rename = "hnd")]
+    HalfNormalDist {
+        diffusion: f64,
+        size: usize,
+        #[serde(default)]
+        mean: XYPoint,
+        side: HalfNormalDistSide,
+    },
+    #[serde(rename = "and")]
+    AnisotropicNormalDist {
+        angle: f64,
+        variances: (f64, f64),
+        #[serde(default)]
+        drift: (f64, f64),
+        size: usize,
+    },
+    #[serde(rename = "lrw")]
+    LatticeRw { lattice: Lattice },
+    #[serde(rename = "lw")]
+    LevyWalk {
+        exponent: f64,
+        max_step_size: usize,
+        #[serde(default)]
+        bias: Option<(Direction, f64)>,
+    },
+    #[serde(rename = "dir")]
+    Directional {
+        heading: f64,
+        heading_distribution: HeadingDistribution,
+        step_length: StepLengthDistribution,
+        size: usize,
+    },
+    #[serde(rename = "csrw")]
+    CorrelatedSectors {
+        sectors: usize,
+        heading_distribution: HeadingDistribution,
+        step_length: StepLengthDistribution,
+        size: usize,
+    },
+    #[serde(rename = "mix")]
+    Mixture {
+        components: Vec<(f64, KernelGeneratorSpec)>,
+    },
+}
+
+impl KernelGeneratorSpec {
+    /// Returns the unique short names of all available generators.
+    pub fn names() -> &'static [&'static str] {
+        &[
+            "srw", "brw", "crw", "bcrw", "nd", "hnd", "and", "lrw", "lw", "dir", "csrw", "mix",
+        ]
+    }
+
+    /// Creates the generator described by the spec.
+    pub fn generator(&self) -> Result<Box<dyn KernelGenerator>, KernelGeneratorError> {
+        Ok(match self.clone() {
+            KernelGeneratorSpec::SimpleRw => Box::new(SimpleRwGenerator),
+            KernelGeneratorSpec::BiasedRw {
+                probability,
+                direction,
+            } => Box::new(BiasedRwGenerator {
+                probability,
+                direction,
+            }),
+            KernelGeneratorSpec::CorrelatedRw { persistence } => {
+                Box::new(CorrelatedRwGenerator { persistence })
+            }
+            KernelGeneratorSpec::BiasedCorrelatedRw {
+                probability,
+                direction,
+                persistence,
+            } => Box::new(BiasedCorrelatedRwGenerator {
+                probability,
+                direction,
+                persistence,
+            }),
+            KernelGeneratorSpec::NormalDist {
+                diffusion,
+                size,
+                mean,
+            } => Box::new(NormalDistGenerator::new(diffusion, size, mean)),
+            KernelGeneratorSpec::HalfNormalDist {
+                diffusion,
+                size,
+                mean,
+                side,
+            } => Box::new(HalfNormalDistGenerator::new(diffusion, size, mean, side)),
+            KernelGeneratorSpec::AnisotropicNormalDist {
+                angle,
+                variances,
+                drift,
+                size,
+            } => Box::new(AnisotropicNormalDistGenerator {
+                angle,
+                variances,
+                drift,
+                size,
+            }),
+            KernelGeneratorSpec::LatticeRw { lattice } => Box::new(LatticeRwGenerator { lattice }),
+            KernelGeneratorSpec::LevyWalk {
+                exponent,
+                max_step_size,
+                bias,
+            } => Box::new(LevyWalkGenerator {
+                exponent,
+                max_step_size,
+                bias,
+            }),
+            KernelGeneratorSpec::Directional {
+                heading,
+                heading_distribution,
+                step_length,
+                size,
+            } => Box::new(DirectionalGenerator {
+                heading,
+                heading_distribution,
+                step_length,
+                size,
+            }),
+            KernelGeneratorSpec::CorrelatedSectors {
+                sectors,
+                heading_distribution,
+                step_length,
+                size,
+            } => {
+                if sectors == 0 {
+                    return Err(KernelGeneratorError::InvalidParameter(
+                        "at least one heading sector is required".into(),
+                    ));
+                }
+
+                Box::new(CorrelatedSectorsGenerator {
+                    sectors: HeadingSectors::new(sectors),
+                    heading_distribution,
+                    step_length,
+                    size,
+                })
+            }
+            KernelGeneratorSpec::Mixture { components } => Box::new(MixtureGenerator {
+                components: components
+                    .iter()
+                    .map(|(weight, spec)| Ok((*weight, spec.generator()?)))
+                    .collect::<Result<_, KernelGeneratorError>>()?,
+            }),
+        })
+    }
+}
+
+impl KernelGenerator for KernelGeneratorSpec {
+    fn prepare(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        self.generator()?.prepare(kernels)
+    }
+
+    fn generate(&self, kernels: &mut Vec<Kernel>) -> Result<(), KernelGeneratorError> {
+        self.generator()?.generate(kernels)
+    }
+
+    // Invalid specs fail in prepare(), so the following values are never used for them
+
+    fn generates_qty(&self) -> usize {
+        self.generator()
+            .map(|generator| generator.generates_qty())
+            .unwrap_or(1)
+    }
+
+    fn name(&self) -> (String, String) {
+        self.generator()
+            .map(|generator| generator.name())
+            .unwrap_or_default()
+    }
+
+    fn lattice(&self) -> Lattice {
+        self.generator()
+            .map(|generator| generator.lattice())
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for KernelGeneratorSpec {
+    type Err = KernelGeneratorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = |e: serde_json::Error| KernelGeneratorError::InvalidSpec(e.to_string());
+
+        if s.starts_with('{') {
+            return serde_json::from_str(s).map_err(invalid);
+        }
+
+        let (name, parameters) = match s.split_once('(') {
+            Some((name, rest)) => match rest.strip_suffix(')') {
+                Some(parameters) => (name.trim(), parameters),
+                None => {
+                    return Err(KernelGeneratorError::InvalidSpec(
+                        "missing closing parenthesis".into(),
+                    ))
+                }
+            },
+            None => (s, ""),
+        };
+
+        let mut object = Map::new();
+        object.insert("generator".into(), Value::String(name.into()));
+
+        for parameter in split_top_level(parameters) {
+            let Some((key, value)) = parameter.split_once('=') else {
+                return Err(KernelGeneratorError::InvalidSpec(format!(
+                    "expected `parameter=value`, found `{parameter}`"
+                )));
+            };
+
+            object.insert(
+                key.trim().into(),
+                serde_json::from_str(value.trim()).map_err(invalid)?,
+            );
+        }
+
+        serde_json::from_value(Value::Object(object)).map_err(invalid)
+    }
+}
+
+impl Display for KernelGeneratorSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Ok(Value::Object(mut object)) = serde_json::to_value(self) else {
+            return Err(std::fmt::Error);
+        };
+        let Some(Value::String(name)) = object.remove("generator") else {
+            return Err(std::fmt::Error);
+        };
+
+        if object.is_empty() {
+            return f.write_str(&name);
+        }
+
+        let parameters: Vec<String> = object
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+
+        write!(f, "{}({})", name, parameters.join(", "))
+    }
+}
+
+/// Splits a list of parameters at all commas that are not nested inside of JSON values.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut start = 0;
+    let mut prev = '\0';
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' if prev != '\\' => in_string = !in_string,
+            '[' | '{' | '(' if !in_string => depth += 1,
+            ']' | '}' | ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+
+        prev = c;
+    }
+
+    parts.push(&s[start..]);
+    parts.retain(|part| !part.trim().is_empty());
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::kernel::correlated_rw::CorrelatedRwGenerator;
+    use crate::kernel::directional::{HeadingDistribution, StepLengthDistribution};
+    use crate::kernel::generator::KernelGenerator;
+    use crate::kernel::half_normal_dist::HalfNormalDistSide;
+    use crate::kernel::spec::KernelGeneratorSpec;
+    use crate::kernel::{Direction, Kernel};
+    use crate::lattice::Lattice;
+
+    fn specs() -> Vec<KernelGeneratorSpec> {
+        let heading_distribution = HeadingDistribution::VonMises { concentration: 1.0 };
+        let step_length = StepLengthDistribution::Gamma {
+            shape: 2.0,
+            scale: 1.0,
+        };
+
+        vec![
+            KernelGeneratorSpec::SimpleRw,
+            KernelGeneratorSpec::BiasedRw {
+                probability: 0.5,
+                direction: Direction::North,
+            },
+            KernelGeneratorSpec::CorrelatedRw { persistence: 0.5 },
+            KernelGeneratorSpec::BiasedCorrelatedRw {
+                probability: 0.5,
+                direction: Direction::East,
+                persistence: 0.5,
+            },
+            KernelGeneratorSpec::NormalDist {
+                diffusion: 1.0,
+                size: 5,
+                mean: Default::default(),
+            },
+            KernelGeneratorSpec::HalfNormalDist {
+                diffusion: 1.0,
+                size: 5,
+                mean: Default::default(),
+                side: HalfNormalDistSide::Left,
+            },
+            KernelGeneratorSpec::AnisotropicNormalDist {
+                angle: 30.0,
+                variances: (2.0, 1.0),
+                drift: (0.5, 0.0),
+                size: 5,
+            },
+            KernelGeneratorSpec::LatticeRw {
+                lattice: Lattice::Hexagonal,
+            },
+            KernelGeneratorSpec::LevyWalk {
+                exponent: 2.0,
+                max_step_size: 3,
+                bias: Some((Direction::South, 1.0)),
+            },
+            KernelGeneratorSpec::Directional {
+                heading: 90.0,
+                heading_distribution,
+                step_length,
+                size: 5,
+            },
+            KernelGeneratorSpec::CorrelatedSectors {
+                sectors: 6,
+                heading_distribution,
+                step_length,
+                size: 5,
+            },
+            KernelGeneratorSpec::Mixture {
+                components: vec![
+                    (0.8, KernelGeneratorSpec::SimpleRw),
+                    (
+                        0.2,
+                        KernelGeneratorSpec::LevyWalk {
+                            exponent: 2.0,
+                            max_step_size: 3,
+                            bias: None,
+                        },
+                    ),
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_spec_names() {
+        let specs = specs();
+        let names: Vec<String> = specs.iter().map(|spec| spec.name().0).collect();
+
+        assert_eq!(names, KernelGeneratorSpec::names());
+        assert_eq!(
+            names.iter().collect::<HashSet<_>>().len(),
+            KernelGeneratorSpec::names().len()
+        );
+
+        for spec in specs {
+            let json = serde_json::to_value(&spec).unwrap();
+
+            assert_eq!(json["generator"], spec.name().0);
+        }
+    }
+
+    #[test]
+    fn test_spec_round_trip() {
+        for spec in specs() {
+            let parsed: KernelGeneratorSpec = spec.to_string().parse().unwrap();
+            let deserialized: KernelGeneratorSpec =
+                serde_json::from_str(&serde_json::to_string(&spec).unwrap()).unwrap();
+
+            assert_eq!(parsed, spec);
+            assert_eq!(deserialized, spec);
+
+            let kernels = Kernel::multiple_from_generator(&spec).unwrap();
+
+            assert_eq!(kernels.len(), spec.generates_qty());
+            assert_eq!(kernels[0].name(true), spec.name().0);
+        }
+
+        assert_eq!(
+            Kernel::multiple_from_generator(
+                "crw(persistence=0.5)"
+                    .parse::<KernelGeneratorSpec>()
+                    .unwrap()
+            )
+            .unwrap(),
+            Kernel::multiple_from_generator(CorrelatedRwGenerator { persistence: 0.5 }).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_spec_parse() {
+        assert_eq!(
+            "srw".parse::<KernelGeneratorSpec>().unwrap(),
+            KernelGeneratorSpec::SimpleRw
+        );
+        assert_eq!(
+            "nd(diffusion=2.0, size=7)"
+                .parse::<KernelGeneratorSpec>()
+                .unwrap(),
+            KernelGeneratorSpec::NormalDist {
+                diffusion: 2.0,
+                size: 7,
+                mean: Default::default(),
+            }
+        );
+        assert_eq!(
+            r#"lw(exponent=1.5, max_step_size=4, bias=["West", 0.5])"#
+                .parse::<KernelGeneratorSpec>()
+                .unwrap(),
+            KernelGeneratorSpec::LevyWalk {
+                exponent: 1.5,
+                max_step_size: 4,
+                bias: Some((Direction::West, 0.5)),
+            }
+        );
+
+        assert!("unknown".parse::<KernelGeneratorSpec>().is_err());
+        assert!("crw".parse::<KernelGeneratorSpec>().is_err());
+        assert!("crw(persistence=0.5"
+            .parse::<KernelGeneratorSpec>()
+            .is_err());
+        assert!("crw(persistence)".parse::<KernelGeneratorSpec>().is_err());
+    }
+}
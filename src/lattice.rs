@@ -0,0 +1,163 @@
+//! Provides the lattice geometries on which random walks can take place.
+//!
+//! All dynamic programs, kernels and walkers store positions as integer `(x, y)` coordinates. How
+//! these coordinates are interpreted is defined by a [`Lattice`]:
+//!
+//! - [`Lattice::Square`]: A square grid where each field has four neighbors (north, east, south and
+//! west). This is the default lattice.
+//! - [`Lattice::SquareDiagonal`]: A square grid where each field has eight neighbors, i.e. diagonal
+//! moves are allowed as well.
+//! - [`Lattice::Hexagonal`]: A hexagonal grid using axial coordinates `(q, r)`, where each field
+//! has six neighbors. Fields are pointy-topped, i.e. the `q` axis points east and the `r` axis
+//! points south-east.
+//!
+//! Since axial coordinates are just a skewed square grid, the tables of dynamic programs and the
+//! probability matrices of kernels are indexed in the same way for all lattices. Only the set of
+//! possible single steps and the conversion into cartesian coordinates differ.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::lattice::Lattice;
+//!
+//! let lattice = Lattice::Hexagonal;
+//!
+//! assert_eq!(lattice.neighbors().len(), 7);
+//! assert_eq!(lattice.distance(2, -1), 2);
+//! assert_eq!(lattice.from_cartesian(lattice.to_cartesian(3, -2)), (3, -2));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+
+/// A lattice geometry on which random walks take place.
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone, Hash, EnumIter, Serialize, Deserialize)]
+pub enum Lattice {
+    /// A square grid with four neighbors per field.
+    #[default]
+    Square,
+
+    /// A square grid with eight neighbors per field, including diagonal neighbors.
+    SquareDiagonal,
+
+    /// A hexagonal grid in axial coordinates with six neighbors per field.
+    Hexagonal,
+}
+
+impl Lattice {
+    /// Returns all possible single steps on the lattice. The first step is always `(0, 0)`, i.e.
+    /// staying on the current field.
+    pub fn neighbors(&self) -> &'static [(isize, isize)] {
+        match self {
+            Lattice::Square => &[(0, 0), (-1, 0), (0, -1), (1, 0), (0, 1)],
+            Lattice::SquareDiagonal => &[
+                (0, 0),
+                (-1, 0),
+                (0, -1),
+                (1, 0),
+                (0, 1),
+                (-1, -1),
+                (1, -1),
+                (1, 1),
+                (-1, 1),
+            ],
+            Lattice::Hexagonal => &[(0, 0), (-1, 0), (0, -1), (1, -1), (1, 0), (0, 1), (-1, 1)],
+        }
+    }
+
+    /// Returns whether `(x, y)` is a possible single step on the lattice, including staying.
+    pub fn is_neighbor(&self, x: isize, y: isize) -> bool {
+        self.neighbors().contains(&(x, y))
+    }
+
+    /// Returns the minimum number of single steps needed to move by `(x, y)` on the lattice.
+    pub fn distance(&self, x: isize, y: isize) -> usize {
+        match self {
+            Lattice::Square => (x.abs() + y.abs()) as usize,
+            Lattice::SquareDiagonal => x.abs().max(y.abs()) as usize,
+            Lattice::Hexagonal => ((x.abs() + y.abs() + (x + y).abs()) / 2) as usize,
+        }
+    }
+
+    /// Converts lattice coordinates into cartesian coordinates, where neighboring fields have a
+    /// distance of `1.0` along the lattice axes.
+    pub fn to_cartesian(&self, x: isize, y: isize) -> (f64, f64) {
+        match self {
+            Lattice::Square | Lattice::SquareDiagonal => (x as f64, y as f64),
+            Lattice::Hexagonal => (x as f64 + y as f64 / 2.0, y as f64 * 3.0f64.sqrt() / 2.0),
+        }
+    }
+
+    /// Converts cartesian coordinates into the coordinates of the lattice field containing them.
+    pub fn from_cartesian(&self, point: (f64, f64)) -> (isize, isize) {
+        let (x, y) = point;
+
+        match self {
+            Lattice::Square | Lattice::SquareDiagonal => (x.round() as isize, y.round() as isize),
+            Lattice::Hexagonal => {
+                let r = y * 2.0 / 3.0f64.sqrt();
+                let q = x - r / 2.0;
+                let s = -q - r;
+
+                // Round in cube coordinates and fix the component with the largest rounding error
+                let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+                let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+                if dq > dr && dq > ds {
+                    rq = -rr - rs;
+                } else if dr > ds {
+                    rr = -rq - rs;
+                }
+
+                (rq as isize, rr as isize)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use crate::lattice::Lattice;
+
+    #[test]
+    fn test_neighbors() {
+        for lattice in Lattice::iter() {
+            let neighbors = lattice.neighbors();
+
+            assert_eq!(neighbors[0], (0, 0));
+
+            for &(x, y) in neighbors.iter().skip(1) {
+                assert_eq!(lattice.distance(x, y), 1);
+                assert!(lattice.is_neighbor(x, y));
+                assert!(lattice.is_neighbor(-x, -y));
+            }
+        }
+
+        assert!(!Lattice::Square.is_neighbor(1, 1));
+        assert!(Lattice::SquareDiagonal.is_neighbor(1, 1));
+        assert!(!Lattice::Hexagonal.is_neighbor(1, 1));
+    }
+
+    #[test]
+    fn test_hexagonal_cartesian() {
+        let lattice = Lattice::Hexagonal;
+
+        // All neighbors have the same euclidean distance
+        for &(x, y) in lattice.neighbors().iter().skip(1) {
+            let (cx, cy) = lattice.to_cartesian(x, y);
+
+            assert!(((cx * cx + cy * cy).sqrt() - 1.0).abs() < 1e-12);
+        }
+
+        for x in -5..=5 {
+            for y in -5..=5 {
+                let (cx, cy) = lattice.to_cartesian(x, y);
+
+                assert_eq!(lattice.from_cartesian((cx, cy)), (x, y));
+                assert_eq!(lattice.from_cartesian((cx + 0.3, cy - 0.2)), (x, y));
+            }
+        }
+    }
+}
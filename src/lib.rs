@@ -41,16 +41,22 @@
 //! # Walkers
 //!
 //! Walkers generate random walks on the basis of a previously computed dynamic program. There
-//! are three different walkers available which do slightly different things.
+//! are different walkers available which do slightly different things.
 //!
-//! - [`StandardWalker`](walker::standard::StandardWalker): The standard walker for generating
-//! random walks that works with all kernels using the `SimpleDynamicProgram`.
+//! - [`BacktrackingWalker`](walker::backtracking::BacktrackingWalker): The standard walker for
+//! generating random walks that works with kernels of any size and shape using the
+//! `SimpleDynamicProgram`, including multi-step and jump kernels.
 //! - [`CorrelatedWalker`](walker::correlated::CorrelatedWalker): A special walker that is designed
 //! to work with the `MultiDynamicProgram` using kernels for correlated random walks. In each step,
 //! it chooses a different dynamic program table depending on the direction of the last step.
-//! - [`MultiStepWalker`](walker::multi_step::MultiStepWalker): Like the `StandardWalker` but it
-//! allows multiple steps to be made at once, making use of dynamic programs that were generated
-//! with kernels larger than 3x3.
+//! - [`MostProbableWalker`](walker::most_probable::MostProbableWalker): Computes the most probable
+//! paths instead of random samples.
+//!
+//! # Lattices
+//!
+//! By default, walks take place on a square grid where each field has four neighbors. Using the
+//! [`Lattice`](lattice::Lattice) of a kernel, walks can also take place on square grids with
+//! diagonal moves or on hexagonal grids. See the [`lattice`] module for more information.
 //!
 //! # Dataset Functionality
 //!
@@ -68,7 +74,8 @@
 //!
 //! This library has the following features which enable additional functionality.
 //!
-//! - `plotting`: Allows generating plots of random walks and datasets and save them as images.
+//! - `plotting`: Allows generating plots of random walks, datasets and kernels and save them as
+//!   images.
 //! - `polars_loading`: Allows loading `DataFrame`s from the
 //! [Polars](https://crates.io/crates/polars) crate.
 //!
@@ -106,7 +113,7 @@
 //! use randomwalks_lib::dp::DynamicPrograms;
 //! use randomwalks_lib::kernel::Kernel;
 //! use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
-//! use randomwalks_lib::walker::standard::StandardWalker;
+//! use randomwalks_lib::walker::backtracking::BacktrackingWalker;
 //! use randomwalks_lib::walker::Walker;
 //!
 //! let mut dp = DynamicProgramBuilder::new()
@@ -118,7 +125,7 @@
 //!
 //! dp.compute();
 //!
-//! let walker = StandardWalker;
+//! let walker = BacktrackingWalker;
 //! let walk = walker.generate_path(&dp, 100, 50, 400).unwrap();
 //! ```
 //!
@@ -131,7 +138,7 @@
 //! ```
 //! use randomwalks_lib::dataset::builder::DatasetBuilder;
 //! use randomwalks_lib::dataset::loader::{ColumnAction, CoordinateType};
-//! use randomwalks_lib::walker::standard::StandardWalker;
+//! use randomwalks_lib::walker::backtracking::BacktrackingWalker;
 //!
 //! let mut dataset = DatasetBuilder::new()
 //!     .from_csv("dataset.csv")
@@ -144,14 +151,17 @@
 //!     .build()
 //!     .unwrap();
 //!
-//! let walker = StandardWalker;
+//! let walker = BacktrackingWalker;
 //! let walk = dataset.rw_between(&dp, Box::new(walker), 0, 1, 400);
 //! ```
 //!
 
 pub mod dataset;
 pub mod dp;
+pub mod fitting;
 pub mod kernel;
+pub mod lattice;
+pub mod likelihood;
 pub mod walk;
 pub mod walker;
 
@@ -0,0 +1,427 @@
+//! Provides functions for scoring walks under a movement model.
+//!
+//! A [`MovementModel`] describes the probability of every single step of a walk. It can either
+//! consist of a single kernel, of a set of kernels which are chosen by the field type of the
+//! field that is moved to, of the [`TransitionModel`](crate::dp::transition::TransitionModel) of a
+//! [`DynamicProgram`] (see [`from_dp()`](MovementModel::from_dp)), or of a set of correlated
+//! kernels which are chosen by the direction of the previous step.
+//!
+//! Using a model, the exact log-likelihood of a [`Walk`] can be computed using
+//! [`log_likelihood()`](MovementModel::log_likelihood). If a dynamic program using the same
+//! model is available, the likelihood of the walk given its start and end point can be computed
+//! using [`conditional_log_likelihood()`](MovementModel::conditional_log_likelihood).
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::dataset::point::XYPoint;
+//! use randomwalks_lib::kernel::Kernel;
+//! use randomwalks_lib::kernel::simple_rw::SimpleRwGenerator;
+//! use randomwalks_lib::likelihood::MovementModel;
+//! use randomwalks_lib::walk::Walk;
+//! use randomwalks_lib::xy;
+//!
+//! let model = MovementModel::Kernel(Kernel::from_generator(SimpleRwGenerator).unwrap());
+//! let walk = Walk(vec![xy!(0, 0), xy!(1, 0), xy!(1, 0), xy!(1, 1)]);
+//!
+//! let likelihood = model.log_likelihood(&walk).unwrap();
+//!
+//! assert!((likelihood.log_likelihood - 3.0 * 0.2f64.ln()).abs() < 1e-12);
+//! ```
+
+use thiserror::Error;
+
+use crate::dataset::point::XYPoint;
+use crate::dp::simple::DynamicProgram;
+use crate::dp::DynamicProgramPool;
+use crate::kernel::correlated_sectors::HeadingSectors;
+use crate::kernel::Kernel;
+use crate::walk::Walk;
+
+/// An error that can occur when computing the likelihood of a walk.
+#[derive(Error, Debug, PartialEq)]
+pub enum LikelihoodError {
+    /// This error occurs when the walk does not contain any steps.
+    #[error("the walk must contain at least two points")]
+    EmptyWalk,
+
+    /// This error occurs when a step of the walk has probability zero under the model.
+    #[error("step {index} from {from:?} to {to:?} is impossible under the model")]
+    ImpossibleStep {
+        index: usize,
+        from: XYPoint,
+        to: XYPoint,
+    },
+
+    /// This error occurs when the conditional likelihood is computed for a walk that does not
+    /// start at the origin of the dynamic program.
+    #[error("the walk must start at (0, 0) to be evaluated using a dynamic program")]
+    StartNotAtOrigin,
+
+    /// This error occurs when the walk is longer than the time limit of the dynamic program.
+    #[error("the walk has more steps than the time limit of the dynamic program")]
+    WalkTooLong,
+
+    /// This error occurs when the end point of the walk cannot be reached according to the
+    /// dynamic program.
+    #[error("the end point of the walk is unreachable according to the dynamic program")]
+    EndPointUnreachable,
+
+    /// This error occurs when a conditional likelihood is requested from a dynamic program pool
+    /// holding multiple dynamic programs.
+    #[error("the conditional likelihood requires a single dynamic program")]
+    RequiresSingleDynamicProgram,
+}
+
+/// The result of a likelihood computation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkLikelihood {
+    /// The natural logarithm of the likelihood of the whole walk.
+    pub log_likelihood: f64,
+
+    /// The natural logarithm of the probability of each single step.
+    pub step_log_probabilities: Vec<f64>,
+}
+
+/// A model describing the probability of single steps of a walk.
+#[derive(Debug, Clone)]
+pub enum MovementModel {
+    /// A single kernel that is used for all steps.
+    Kernel(Kernel),
+
+    /// A set of kernels that is chosen by the field type of the field that is moved to, like in
+    /// [`DynamicProgram`]. `field_types` is indexed such that `field_types[n / 2][n / 2]` is the
+    /// origin `(0, 0)` of the walk, where `n` is the length of `field_types`. Steps leaving the
+    /// area covered by `field_types` are impossible.
+    FieldTypes {
+        kernels: Vec<(usize, Kernel)>,
+        field_types: Vec<Vec<usize>>,
+    },
+
+    /// A set of correlated kernels, e.g. generated by
+    /// [`CorrelatedRwGenerator`](crate::kernel::correlated_rw::CorrelatedRwGenerator) or
+    /// [`CorrelatedSectorsGenerator`](crate::kernel::correlated_sectors::CorrelatedSectorsGenerator).
+    /// The kernel for each step is chosen by the variant of the previous step according to
+    /// [`HeadingSectors`] with one sector less than there are kernels. Since the first step has no
+    /// previous step, it uses the average of all kernels.
+    Correlated(Vec<Kernel>),
+
+    /// The transition model of a dynamic program, created using
+    /// [`from_dp()`](MovementModel::from_dp).
+    Transitions(DynamicProgramTransitions),
+}
+
+/// The transition model of a [`DynamicProgram`] without its table.
+///
+/// Steps are scored using [`DynamicProgram::transition_probability()`], so the kernel selection,
+/// crossing weights, habitat weights, normalization and barriers of the dynamic program are taken
+/// into account. Steps leaving the table of the dynamic program are impossible.
+#[derive(Debug, Clone)]
+pub struct DynamicProgramTransitions {
+    dp: DynamicProgram,
+}
+
+impl MovementModel {
+    /// Creates a model scoring each step the same way as a [`DynamicProgram`] and its walkers, see
+    /// [`DynamicProgramTransitions`].
+    pub fn from_dp(dp: &DynamicProgram) -> Self {
+        MovementModel::Transitions(DynamicProgramTransitions {
+            dp: DynamicProgram {
+                table: Vec::new(),
+                time_limit: dp.time_limit,
+                kernels: dp.kernels.clone(),
+                field_types: dp.field_types.clone(),
+                lattice: dp.lattice,
+                transition: dp.transition.clone(),
+            },
+        })
+    }
+
+    /// Computes the probability of moving from `from` to `to`. `previous` is the previous step of
+    /// the walk, if any.
+    pub fn step_probability(&self, from: XYPoint, to: XYPoint, previous: Option<XYPoint>) -> f64 {
+        let (dx, dy) = ((to.x - from.x) as isize, (to.y - from.y) as isize);
+
+        match self {
+            MovementModel::Kernel(kernel) => kernel.at_or(dx, dy, 0.0),
+            MovementModel::FieldTypes {
+                kernels,
+                field_types,
+            } => {
+                let center = (field_types.len() / 2) as i64;
+                let (x, y) = (center + to.x, center + to.y);
+
+                if x < 0 || y < 0 || x >= field_types.len() as i64 {
+                    return 0.0;
+                }
+
+                let Some(field_type) = field_types[x as usize].get(y as usize) else {
+                    return 0.0;
+                };
+
+                match kernels.iter().find(|(ft, _)| ft == field_type) {
+                    Some((_, kernel)) => kernel.at_or(dx, dy, 0.0),
+                    None => 0.0,
+                }
+            }
+            MovementModel::Correlated(kernels) => match previous {
+                Some(previous) => {
+                    let sectors = HeadingSectors::new(kernels.len().saturating_sub(1).max(1));
+                    let variant = sectors.variant(previous.x as isize, previous.y as isize);
+
+                    kernels
+                        .get(variant)
+                        .map_or(0.0, |kernel| kernel.at_or(dx, dy, 0.0))
+                }
+                None => {
+                    kernels
+                        .iter()
+                        .map(|kernel| kernel.at_or(dx, dy, 0.0))
+                        .sum::<f64>()
+                        / kernels.len() as f64
+                }
+            },
+            MovementModel::Transitions(transitions) => transitions.dp.transition_probability(
+                from.x as isize,
+                from.y as isize,
+                to.x as isize,
+                to.y as isize,
+            ),
+        }
+    }
+
+    /// Computes the exact log-likelihood of the step sequence of `walk`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LikelihoodError::ImpossibleStep`] containing the index of the first step that
+    /// has probability zero under the model.
+    pub fn log_likelihood(&self, walk: &Walk) -> Result<WalkLikelihood, LikelihoodError> {
+        if walk.len() < 2 {
+            return Err(LikelihoodError::EmptyWalk);
+        }
+
+        let mut step_log_probabilities = Vec::with_capacity(walk.len() - 1);
+        let mut previous = None;
+
+        for (index, pair) in walk.0.windows(2).enumerate() {
+            let (from, to) = (pair[0], pair[1]);
+            let probability = self.step_probability(from, to, previous);
+
+            if probability <= 0.0 || !probability.is_finite() {
+                return Err(LikelihoodError::ImpossibleStep { index, from, to });
+            }
+
+            step_log_probabilities.push(probability.ln());
+            previous = Some(to - from);
+        }
+
+        Ok(WalkLikelihood {
+            log_likelihood: step_log_probabilities.iter().sum(),
+            step_log_probabilities,
+        })
+    }
+
+    /// Computes the log-likelihood of the step sequence of `walk` conditioned on its start and
+    /// end point.
+    ///
+    /// The walk must start at `(0, 0)` and `dp` must have been computed using the same model.
+    /// The probability of reaching the end point of the walk in the number of steps of the walk
+    /// is then taken from the dynamic program and used for normalization.
+    pub fn conditional_log_likelihood(
+        &self,
+        walk: &Walk,
+        dp: &DynamicProgramPool,
+    ) -> Result<WalkLikelihood, LikelihoodError> {
+        let DynamicProgramPool::Single(dp) = dp else {
+            return Err(LikelihoodError::RequiresSingleDynamicProgram);
+        };
+
+        if walk.len() < 2 {
+            return Err(LikelihoodError::EmptyWalk);
+        }
+
+        if walk[0] != XYPoint::default() {
+            return Err(LikelihoodError::StartNotAtOrigin);
+        }
+
+        let steps = walk.len() - 1;
+
+        if steps > dp.time_limit {
+            return Err(LikelihoodError::WalkTooLong);
+        }
+
+        let end = walk[walk.len() - 1];
+        let end_probability = dp.at_or(end.x as isize, end.y as isize, steps, 0.0);
+
+        if end_probability <= 0.0 {
+            return Err(LikelihoodError::EndPointUnreachable);
+        }
+
+        let mut likelihood = self.log_likelihood(walk)?;
+        likelihood.log_likelihood -= end_probability.ln();
+
+        Ok(likelihood)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::point::XYPoint;
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::transition::KernelSelection;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::correlated_rw::CorrelatedRwGenerator;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::Kernel;
+    use crate::likelihood::{LikelihoodError, MovementModel};
+    use crate::walk::Walk;
+    use crate::xy;
+
+    #[test]
+    fn test_impossible_step() {
+        let model = MovementModel::Kernel(Kernel::from_generator(SimpleRwGenerator).unwrap());
+        let walk = Walk(vec![xy!(0, 0), xy!(1, 0), xy!(2, 1), xy!(2, 2)]);
+
+        assert_eq!(
+            model.log_likelihood(&walk),
+            Err(LikelihoodError::ImpossibleStep {
+                index: 1,
+                from: xy!(1, 0),
+                to: xy!(2, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn test_field_types() {
+        let mut field_types = vec![vec![0; 5]; 5];
+        field_types[3][2] = 1;
+
+        let model = MovementModel::FieldTypes {
+            kernels: vec![
+                (0, Kernel::from_generator(SimpleRwGenerator).unwrap()),
+                (1, Kernel::try_from_value(3, 0.0).unwrap()),
+            ],
+            field_types,
+        };
+
+        let allowed = Walk(vec![xy!(0, 0), xy!(0, 1), xy!(1, 1)]);
+        let blocked = Walk(vec![xy!(0, 0), xy!(1, 0)]);
+        let outside = Walk(vec![xy!(0, 0), xy!(0, 1), xy!(0, 2), xy!(0, 3)]);
+
+        assert!(model.log_likelihood(&allowed).is_ok());
+        assert!(matches!(
+            model.log_likelihood(&blocked),
+            Err(LikelihoodError::ImpossibleStep { index: 0, .. })
+        ));
+        assert!(matches!(
+            model.log_likelihood(&outside),
+            Err(LikelihoodError::ImpossibleStep { index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_correlated() {
+        let kernels =
+            Kernel::multiple_from_generator(CorrelatedRwGenerator { persistence: 0.5 }).unwrap();
+        let model = MovementModel::Correlated(kernels);
+        let walk = Walk(vec![xy!(0, 0), xy!(0, -1), xy!(0, -2), xy!(1, -2)]);
+
+        let likelihood = model.log_likelihood(&walk).unwrap();
+        let expected = [0.2f64.ln(), 0.5f64.ln(), 0.125f64.ln()];
+
+        for (step, expected) in likelihood.step_log_probabilities.iter().zip(expected) {
+            assert!((step - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_conditional() {
+        let kernel = Kernel::from_generator(SimpleRwGenerator).unwrap();
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(kernel.clone())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let model = MovementModel::Kernel(kernel);
+
+        // Only a single path of length 2 leads to (2, 0)
+        let walk = Walk(vec![xy!(0, 0), xy!(1, 0), xy!(2, 0)]);
+        let likelihood = model.conditional_log_likelihood(&walk, &dp).unwrap();
+
+        assert!(likelihood.log_likelihood.abs() < 1e-12);
+
+        let walk = Walk(vec![xy!(1, 0), xy!(2, 0)]);
+
+        assert_eq!(
+            model.conditional_log_likelihood(&walk, &dp),
+            Err(LikelihoodError::StartNotAtOrigin)
+        );
+    }
+
+    #[test]
+    fn test_from_dp() {
+        // Fields with x > 0 are left using jumps of two fields and entered at a cost
+        let field_types: Vec<Vec<usize>> = (0..21)
+            .map(|x| vec![if x > 10 { 1 } else { 0 }; 21])
+            .collect();
+        let jumps = Kernel::from_entries(
+            2,
+            &[(2, 0, 0.25), (-2, 0, 0.25), (0, 2, 0.25), (0, -2, 0.25)],
+        )
+        .unwrap();
+
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernels(vec![
+                (0, Kernel::from_generator(SimpleRwGenerator).unwrap()),
+                (1, jumps),
+            ])
+            .field_types(field_types)
+            .kernel_selection(KernelSelection::Source)
+            .crossing_weight(0, 1, 0.5)
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let DynamicProgramPool::Single(dp) = dp else {
+            unreachable!();
+        };
+
+        let model = MovementModel::from_dp(&dp);
+        let walk = Walk(vec![xy!(0, 0), xy!(1, 0), xy!(3, 0), xy!(3, 2)]);
+        let likelihood = model.log_likelihood(&walk).unwrap();
+        let expected = [0.1f64.ln(), 0.25f64.ln(), 0.25f64.ln()];
+
+        for (step, expected) in likelihood.step_log_probabilities.iter().zip(expected) {
+            assert!((step - expected).abs() < 1e-12);
+        }
+
+        for pair in walk.0.windows(2) {
+            assert_eq!(
+                model.step_probability(pair[0], pair[1], None),
+                dp.transition_probability(
+                    pair[0].x as isize,
+                    pair[0].y as isize,
+                    pair[1].x as isize,
+                    pair[1].y as isize
+                )
+            );
+        }
+
+        // Steps use the kernel of the field that is left
+        let walk = Walk(vec![xy!(0, 0), xy!(1, 0), xy!(2, 0)]);
+
+        assert!(matches!(
+            model.log_likelihood(&walk),
+            Err(LikelihoodError::ImpossibleStep { index: 1, .. })
+        ));
+    }
+}
@@ -17,8 +17,7 @@ use plotters::backend::BitMapBackend;
 use plotters::chart::ChartBuilder;
 use plotters::drawing::IntoDrawingArea;
 use plotters::element::{Circle, EmptyElement, Text};
-use plotters::prelude::{IntoFont, LineSeries, PointSeries, RGBColor, BLACK, WHITE};
-use rand::Rng;
+use plotters::prelude::{IntoFont, LineSeries, Palette, Palette99, PointSeries, BLACK, WHITE};
 
 use crate::dataset::point::XYPoint;
 
@@ -236,17 +235,8 @@ impl Walk {
             .map(|w| w.iter().map(|p| (p.x, p.y)).collect())
             .collect();
 
-        let mut rng = rand::thread_rng();
-
-        for walk in walks.iter() {
-            chart.draw_series(LineSeries::new(
-                walk.clone(),
-                RGBColor(
-                    rng.gen_range(30..220),
-                    rng.gen_range(30..220),
-                    rng.gen_range(30..220),
-                ),
-            ))?;
+        for (i, walk) in walks.iter().enumerate() {
+            chart.draw_series(LineSeries::new(walk.clone(), Palette99::pick(i)))?;
         }
 
         // Find unique start and end points
@@ -279,6 +269,94 @@ impl Walk {
     }
 }
 
+/// A random walk whose points are labelled, e.g. with the behavioral state the walk was in.
+///
+/// The label of each point belongs to the step leading to that point.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct LabelledWalk {
+    pub walk: Walk,
+    pub labels: Vec<usize>,
+}
+
+impl LabelledWalk {
+    /// Creates a labelled walk. Returns an error if the number of labels and points differ.
+    pub fn try_new(walk: Walk, labels: Vec<usize>) -> anyhow::Result<Self> {
+        if walk.len() != labels.len() {
+            bail!(
+                "walk has {} points but {} labels were given",
+                walk.len(),
+                labels.len()
+            );
+        }
+
+        Ok(Self { walk, labels })
+    }
+
+    // Returns the number of points in the walk.
+    pub fn len(&self) -> usize {
+        self.walk.len()
+    }
+
+    // Returns whether the walk contains any points.
+    pub fn is_empty(&self) -> bool {
+        self.walk.is_empty()
+    }
+
+    /// Returns an iterator over all points together with their labels.
+    pub fn iter(&self) -> impl Iterator<Item = (&XYPoint, usize)> {
+        self.walk.iter().zip(self.labels.iter().copied())
+    }
+
+    /// Plots a walk with each step colored by its label and saves the resulting image to a .png
+    /// file.
+    #[cfg(feature = "plotting")]
+    pub fn plot<S: Into<String>>(&self, filename: S) -> anyhow::Result<()> {
+        if self.walk.is_empty() {
+            bail!("Cannot plot empty walk");
+        }
+
+        let filename = filename.into();
+
+        // Initialize plot
+
+        let (coordinate_range_x, coordinate_range_y) = point_range(&[self.walk.clone()]);
+
+        let root = BitMapBackend::new(&filename, (1000, 1000)).into_drawing_area();
+        root.fill(&WHITE).unwrap();
+        let root = root.margin(10, 10, 10, 10);
+
+        let mut chart = ChartBuilder::on(&root)
+            .x_label_area_size(20)
+            .y_label_area_size(20)
+            .build_cartesian_2d(coordinate_range_x, coordinate_range_y)?;
+
+        chart.configure_mesh().draw()?;
+
+        // Draw steps colored by the label of their end point
+
+        let walk: Vec<(i64, i64)> = self.walk.iter().map(|x| (*x).into()).collect();
+
+        for (step, label) in walk.windows(2).zip(self.labels.iter().skip(1)) {
+            chart.draw_series(LineSeries::new(step.to_vec(), Palette99::pick(*label)))?;
+        }
+
+        // Draw start and end point
+
+        chart.draw_series(PointSeries::of_element(
+            vec![*walk.first().unwrap(), *walk.last().unwrap()],
+            5,
+            &BLACK,
+            &|c, s, st| {
+                EmptyElement::at(c)
+                    + Circle::new((0, 0), s, st.filled())
+                    + Text::new(format!("{:?}", c), (10, 0), ("sans-serif", 10).into_font())
+            },
+        ))?;
+
+        Ok(())
+    }
+}
+
 #[cfg(feature = "plotting")]
 fn point_range(walks: &[Walk]) -> (Range<i64>, Range<i64>) {
     // Compute size of plotting area
@@ -345,7 +423,7 @@ impl Index<usize> for Walk {
 #[cfg(test)]
 mod tests {
     use crate::dataset::point::XYPoint;
-    use crate::walk::Walk;
+    use crate::walk::{LabelledWalk, Walk};
     use crate::xy;
 
     #[test]
@@ -364,6 +442,16 @@ mod tests {
         assert_eq!(walk1, walk2);
     }
 
+    #[test]
+    fn test_labelled_walk() {
+        let walk = Walk(vec![xy!(0, 0), xy!(1, 0), xy!(1, 0)]);
+        let labelled = LabelledWalk::try_new(walk.clone(), vec![0, 1, 0]).unwrap();
+
+        assert_eq!(labelled.len(), 3);
+        assert_eq!(labelled.iter().nth(1), Some((&xy!(1, 0), 1)));
+        assert!(LabelledWalk::try_new(walk, vec![0, 1]).is_err());
+    }
+
     #[test]
     fn test_walk_rotate() {
         let walk1 = Walk(vec![xy!(0, 0), xy!(2, 3), xy!(7, 5)]).rotate(90.0);
@@ -0,0 +1,458 @@
+//! Provides a generic walker sampling steps from the kernels of the dynamic program.
+//!
+//! The [`BacktrackingWalker`] samples each step backwards, starting at the end point. The
+//! candidate predecessors of a field are derived from the [support](crate::kernel::Kernel::support)
+//! of the kernel the dynamic program uses for that field, see
+//! [`DynamicProgram::predecessors()`](crate::dp::simple::DynamicProgram::predecessors), and are
+//! weighted using the same transition probabilities as the dynamic program, see
+//! [`DynamicProgram::transition_probability()`](crate::dp::simple::DynamicProgram::transition_probability).
+//!
+//! Therefore, no neighborhood or maximum step size has to be given and kernels of any size and
+//! shape work, including multi-step, jump and sparse kernels. Different kernels per field type,
+//! barriers, crossing weights, habitat weights and the
+//! [`KernelSelection`](crate::dp::transition::KernelSelection) are taken into account as well.
+//! It replaces the `StandardWalker`, `MultiStepWalker`, `LevyWalker` and `LandCoverWalker`, which
+//! sampled steps the same way and are now deprecated aliases of it.
+//!
+//! The returned walks contain `time_steps + 1` points, i.e. exactly `time_steps` steps.
+//!
+//! # Examples
+//!
+//! ```
+//! use randomwalks_lib::dp::builder::DynamicProgramBuilder;
+//! use randomwalks_lib::dp::DynamicPrograms;
+//! use randomwalks_lib::kernel::Kernel;
+//! use randomwalks_lib::walker::backtracking::BacktrackingWalker;
+//! use randomwalks_lib::walker::Walker;
+//!
+//! // Jumps of three fields in each direction
+//! let jumps = vec![(3, 0, 0.25), (-3, 0, 0.25), (0, 3, 0.25), (0, -3, 0.25)];
+//!
+//! let mut dp = DynamicProgramBuilder::new()
+//!     .simple()
+//!     .time_limit(12)
+//!     .kernel(Kernel::from_entries(3, &jumps).unwrap())
+//!     .build()
+//!     .unwrap();
+//!
+//! dp.compute();
+//!
+//! let walk = BacktrackingWalker.generate_path(&dp, 6, -3, 5).unwrap();
+//!
+//! assert_eq!(walk.len(), 6);
+//! ```
+
+use num::Zero;
+use rand::distributions::{WeightedError, WeightedIndex};
+use rand::prelude::*;
+
+use crate::dp::simple::DynamicProgram;
+use crate::dp::DynamicProgramPool;
+use crate::walker::{Walk, Walker, WalkerError};
+
+/// A walker sampling steps from the kernels of the dynamic program.
+///
+/// For details see the [`backtracking`](crate::walker::backtracking) module.
+#[derive(Clone)]
+pub struct BacktrackingWalker;
+
+impl Walker for BacktrackingWalker {
+    fn generate_path_with_rng(
+        &self,
+        dp: &DynamicProgramPool,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Walk, WalkerError> {
+        let DynamicProgramPool::Single(dp) = dp else {
+            return Err(WalkerError::RequiresSingleDynamicProgram);
+        };
+
+        backtrack(dp, to_x, to_y, time_steps, rng)
+    }
+
+    fn name(&self, short: bool) -> String {
+        if short {
+            String::from("btw")
+        } else {
+            String::from("Backtracking Walker")
+        }
+    }
+}
+
+/// Samples a path from the origin to `(to_x, to_y)` in `time_steps` time steps by sampling each
+/// step backwards from the predecessors of the current field.
+pub(crate) fn backtrack(
+    dp: &DynamicProgram,
+    to_x: isize,
+    to_y: isize,
+    time_steps: usize,
+    rng: &mut dyn RngCore,
+) -> Result<Walk, WalkerError> {
+    if time_steps > dp.time_limit {
+        return Err(WalkerError::TimeLimitExceeded);
+    }
+
+    let mut path = Vec::new();
+    let (mut x, mut y) = (to_x, to_y);
+
+    // Check if any path exists leading to the given end point
+    if dp.at_or(to_x, to_y, time_steps, 0.0).is_zero() {
+        return Err(WalkerError::NoPathExists);
+    }
+
+    for t in (1..=time_steps).rev() {
+        path.push((x as i64, y as i64).into());
+
+        let mut prev_probs = Vec::new();
+        let mut movements = Vec::new();
+
+        for (i, j) in dp.predecessors(x, y) {
+            let p_b = dp.at_or(i, j, t - 1, 0.0);
+            let p_a_b = dp.transition_probability(i, j, x, y);
+
+            prev_probs.push(p_a_b * p_b);
+            movements.push((i - x, j - y));
+        }
+
+        let direction = match WeightedIndex::new(prev_probs) {
+            Ok(dist) => dist.sample(rng),
+            Err(WeightedError::AllWeightsZero) => return Err(WalkerError::InconsistentPath),
+            _ => return Err(WalkerError::RandomDistributionError),
+        };
+        let (dx, dy) = movements[direction];
+
+        x += dx;
+        y += dy;
+    }
+
+    path.reverse();
+    path.insert(0, (x as i64, y as i64).into());
+
+    Ok(path.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::dataset::point::XYPoint;
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::transition::KernelSelection;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::lattice_rw::LatticeRwGenerator;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::Kernel;
+    use crate::lattice::Lattice;
+    use crate::walker::backtracking::BacktrackingWalker;
+    use crate::walker::{Walker, WalkerError};
+    use crate::{kernel, xy};
+
+    #[test]
+    fn test_backtracking_walker_field_types() {
+        // Fields with x > 0 are crossed using knight's moves of a chess board
+        let field_types: Vec<Vec<usize>> = (0..31)
+            .map(|x| vec![if x > 15 { 1 } else { 0 }; 31])
+            .collect();
+        let knight = Kernel::from_entries(
+            2,
+            &[
+                (1, 2, 0.125),
+                (2, 1, 0.125),
+                (2, -1, 0.125),
+                (1, -2, 0.125),
+                (-1, -2, 0.125),
+                (-2, -1, 0.125),
+                (-2, 1, 0.125),
+                (-1, 2, 0.125),
+            ],
+        )
+        .unwrap();
+
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(15)
+            .kernels(vec![
+                (0, Kernel::from_generator(SimpleRwGenerator).unwrap()),
+                (1, knight.clone()),
+            ])
+            .field_types(field_types)
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let walks = BacktrackingWalker
+            .generate_paths_seeded(&dp, 20, 7, 3, 12, 42)
+            .unwrap();
+
+        assert!(matches!(
+            BacktrackingWalker.generate_path(&dp, 7, 3, 16),
+            Err(WalkerError::TimeLimitExceeded)
+        ));
+
+        let DynamicProgramPool::Single(dp) = dp else {
+            unreachable!();
+        };
+
+        for walk in walks.iter() {
+            assert_eq!(walk.len(), 13);
+            assert_eq!((walk[0].x, walk[0].y), (0, 0));
+            assert_eq!((walk[12].x, walk[12].y), (7, 3));
+
+            // Steps use the kernel of the field that is entered
+            for step in walk.0.windows(2) {
+                let (dx, dy) = (step[1].x - step[0].x, step[1].y - step[0].y);
+
+                if step[1].x > 0 {
+                    assert!(knight.at(dx as isize, dy as isize) > 0.0);
+                } else {
+                    assert!(dx.abs() + dy.abs() <= 1);
+                }
+
+                assert!(
+                    dp.transition_probability(
+                        step[0].x as isize,
+                        step[0].y as isize,
+                        step[1].x as isize,
+                        step[1].y as isize
+                    ) > 0.0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_backtracking_walker_habitat_weights() {
+        // Fields with x > 0 are avoided
+        let weights: Vec<Vec<f64>> = (0..21)
+            .map(|x| vec![if x > 10 { 0.01 } else { 1.0 }; 21])
+            .collect();
+
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .habitat_weights(weights)
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let walks = BacktrackingWalker
+            .generate_paths_seeded(&dp, 50, 0, 5, 10, 42)
+            .unwrap();
+        let (mut east, mut west) = (0, 0);
+
+        for walk in walks.iter() {
+            assert_eq!(walk.len(), 11);
+            assert_eq!((walk[0].x, walk[0].y), (0, 0));
+            assert_eq!((walk[10].x, walk[10].y), (0, 5));
+
+            for step in walk.0.windows(2) {
+                assert!((step[1].x - step[0].x).abs() + (step[1].y - step[0].y).abs() <= 1);
+            }
+
+            east += walk.iter().filter(|p| p.x > 0).count();
+            west += walk.iter().filter(|p| p.x < 0).count();
+        }
+
+        assert!(west > 10 * east);
+
+        assert!(BacktrackingWalker.generate_path(&dp, 0, 5, 11).is_err());
+        assert!(BacktrackingWalker.generate_path(&dp, 0, 0, 1).is_ok());
+    }
+
+    #[test]
+    fn test_backtracking_walker_hexagonal() {
+        let kernel = Kernel::from_generator(LatticeRwGenerator {
+            lattice: Lattice::Hexagonal,
+        })
+        .unwrap();
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(20)
+            .kernel(kernel)
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..10 {
+            let walk = BacktrackingWalker
+                .generate_path_with_rng(&dp, 5, -3, 20, &mut rng)
+                .unwrap();
+
+            assert_eq!(walk[0], (0, 0).into());
+            assert_eq!(walk[walk.len() - 1], (5, -3).into());
+
+            for i in 1..walk.len() {
+                let (dx, dy) = (walk[i].x - walk[i - 1].x, walk[i].y - walk[i - 1].y);
+
+                assert!(Lattice::Hexagonal.is_neighbor(dx as isize, dy as isize));
+            }
+        }
+    }
+
+    #[test]
+    fn test_backtracking_walker_barriers() {
+        // A wall at x = 2 with a single gap at y = 4
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(15)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .add_rect_barrier(xy!(2, -15), xy!(2, 3))
+            .add_rect_barrier(xy!(2, 5), xy!(2, 15))
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        for walk in BacktrackingWalker
+            .generate_paths_seeded(&dp, 20, 4, 0, 15, 42)
+            .unwrap()
+        {
+            assert_eq!(walk[walk.len() - 1], xy!(4, 0));
+            assert!(walk.iter().any(|point| *point == xy!(2, 4)));
+            assert!(walk.iter().all(|point| point.x != 2 || point.y == 4));
+        }
+    }
+
+    #[test]
+    fn test_backtracking_walker_transition_model() {
+        // Fields with x > 0 are left using jumps of two fields and cannot be left into x <= 0
+        let field_types: Vec<Vec<usize>> = (0..21)
+            .map(|x| vec![if x > 10 { 1 } else { 0 }; 21])
+            .collect();
+        let jumps = Kernel::from_entries(
+            2,
+            &[(2, 0, 0.25), (-2, 0, 0.25), (0, 2, 0.25), (0, -2, 0.25)],
+        )
+        .unwrap();
+
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernels(vec![
+                (0, Kernel::from_generator(SimpleRwGenerator).unwrap()),
+                (1, jumps),
+            ])
+            .field_types(field_types)
+            .kernel_selection(KernelSelection::Source)
+            .crossing_weight(1, 0, 0.0)
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let walks = BacktrackingWalker
+            .generate_paths_seeded(&dp, 20, 5, 1, 9, 42)
+            .unwrap();
+
+        let DynamicProgramPool::Single(dp) = &dp else {
+            unreachable!();
+        };
+
+        for walk in walks.iter() {
+            assert_eq!(walk.len(), 10);
+            assert_eq!(walk[0], xy!(0, 0));
+            assert_eq!(walk[9], xy!(5, 1));
+
+            for step in walk.0.windows(2) {
+                let (dx, dy) = (step[1].x - step[0].x, step[1].y - step[0].y);
+
+                // Steps use the kernel of the field that is left
+                if step[0].x > 0 {
+                    assert!(step[1].x > 0);
+                    assert_eq!(dx.abs() + dy.abs(), 2);
+                } else {
+                    assert!(dx.abs() + dy.abs() <= 1);
+                }
+
+                assert!(
+                    dp.transition_probability(
+                        step[0].x as isize,
+                        step[0].y as isize,
+                        step[1].x as isize,
+                        step[1].y as isize
+                    ) > 0.0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_backtracking_walker_jumps_hexagonal() {
+        // Single steps or jumps of three fields to any neighbor on a hexagonal lattice
+        let mut entries = vec![(0, 0, 0.3)];
+
+        for &(dx, dy) in Lattice::Hexagonal.neighbors().iter().skip(1) {
+            entries.push((dx, dy, 0.1));
+            entries.push((3 * dx, 3 * dy, 0.1 / 6.0));
+        }
+
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(
+                Kernel::from_entries(3, &entries)
+                    .unwrap()
+                    .with_lattice(Lattice::Hexagonal),
+            )
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        for walk in BacktrackingWalker
+            .generate_paths_seeded(&dp, 20, 4, -7, 8, 42)
+            .unwrap()
+        {
+            assert_eq!(walk.len(), 9);
+            assert_eq!(walk[8], (4, -7).into());
+
+            for step in walk.0.windows(2) {
+                let (dx, dy) = (step[1].x - step[0].x, step[1].y - step[0].y);
+                let (dx, dy) = (dx as isize, dy as isize);
+                let jump = dx % 3 == 0 && dy % 3 == 0;
+
+                assert!(
+                    Lattice::Hexagonal.is_neighbor(dx, dy)
+                        || (jump && Lattice::Hexagonal.is_neighbor(dx / 3, dy / 3))
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_backtracking_walker_orientation() {
+        // Walks can only stay or go east, north or south
+        let kernel = kernel![
+            0.0, 0.25, 0.0,
+            0.0, 0.1, 0.4,
+            0.0, 0.25, 0.0,
+        ];
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(kernel)
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let walk = BacktrackingWalker
+            .generate_path_with_rng(&dp, 5, 1, 7, &mut rng)
+            .unwrap();
+
+        assert_eq!(walk[0], xy!(0, 0));
+        assert_eq!(walk[walk.len() - 1], xy!(5, 1));
+        assert!(walk.iter().zip(walk.iter().skip(1)).all(|(a, b)| b.x >= a.x));
+    }
+}
@@ -1,24 +1,43 @@
 use num::Zero;
 use rand::distributions::{WeightedError, WeightedIndex};
 use rand::prelude::Distribution;
-use rand::Rng;
+use rand::{Rng, RngCore};
 
-use crate::dp::simple::DynamicProgram;
 use crate::dp::DynamicProgramPool;
+use crate::kernel::correlated_sectors::HeadingSectors;
 use crate::kernel::Kernel;
-use crate::walker::{Walk, Walker, WalkerError};
-
+use crate::lattice::Lattice;
+use crate::walker::{variant_predecessors, Walk, Walker, WalkerError};
+
+/// A walker for correlated random walks on square lattices.
+///
+/// The dynamic programs and kernels must be ordered like `Direction::iter()`, e.g. as generated
+/// by a [`CorrelatedRwGenerator`](crate::kernel::correlated_rw::CorrelatedRwGenerator), which is
+/// the order of the variants of four [`HeadingSectors`]. In each step, the dynamic program is
+/// chosen by the direction of the step that follows it. Steps are
+/// sampled using the transition probabilities of the chosen dynamic program if it is held in
+/// memory and using the corresponding kernel otherwise. Like all walkers, it returns walks with
+/// `time_steps + 1` points.
 pub struct CorrelatedWalker {
     pub kernels: Vec<Kernel>,
 }
 
+impl CorrelatedWalker {
+    /// Returns the variant used for the movement preceding a movement by `(mov_x, mov_y)` that was
+    /// sampled backwards, i.e. the variant of the heading of the reversed movement.
+    fn variant(mov_x: isize, mov_y: isize) -> usize {
+        HeadingSectors::new(4).variant(-mov_x, -mov_y)
+    }
+}
+
 impl Walker for CorrelatedWalker {
-    fn generate_path(
+    fn generate_path_with_rng(
         &self,
         dp: &DynamicProgramPool,
         to_x: isize,
         to_y: isize,
         time_steps: usize,
+        rng: &mut dyn RngCore,
     ) -> Result<Walk, WalkerError> {
         if matches!(dp, DynamicProgramPool::Single(_)) {
             return Err(WalkerError::RequiresMultipleDynamicPrograms);
@@ -32,70 +51,59 @@ impl Walker for CorrelatedWalker {
 
         let mut path = Vec::new();
         let (mut x, mut y) = (to_x, to_y);
-        let mut rng = rand::thread_rng();
-
-        // Check if any path exists leading to the given end point for each variant
-        for variant in 0..dp_qty {
-            if dp.at(to_x, to_y, time_steps, variant).unwrap().is_zero() {
-                return Err(WalkerError::NoPathExists);
-            }
+        let lattice = self
+            .kernels
+            .first()
+            .map(Kernel::lattice)
+            .unwrap_or_default();
+        let neighbors = lattice.neighbors();
+
+        // Kernels of correlated random walks are only defined for the four directions of square
+        // lattices
+        if lattice != Lattice::Square {
+            return Err(WalkerError::UnsupportedLattice(lattice));
         }
 
-        path.push((x as i64, y as i64).into());
-
-        // Compute first (= last, because reconstructing backwards) step manually
-        let direction: usize = rng.gen_range(0..4);
+        // Check if any path exists leading to the given end point for any variant
+        let variants: Vec<usize> = (0..dp_qty)
+            .filter(|&i| !dp.at(to_x, to_y, time_steps, i).unwrap().is_zero())
+            .collect();
 
-        match direction {
-            1 => x -= 1,
-            2 => y -= 1,
-            3 => x += 1,
-            4 => y += 1,
-            _ => (),
+        if variants.is_empty() {
+            return Err(WalkerError::NoPathExists);
         }
 
-        let mut last_direction = direction;
+        // The direction after the last step is unknown, so start with a random variant
+        let mut variant = variants[rng.gen_range(0..variants.len())];
 
-        for t in (1..time_steps - 1).rev() {
+        for t in (1..=time_steps).rev() {
             path.push((x as i64, y as i64).into());
 
-            let variant: usize = match last_direction {
-                0 => 4,
-                1 => 1,
-                2 => 0,
-                3 => 3,
-                4 => 2,
-                _ => panic!("Invalid last direction. This should not happen."),
-            };
-
-            let neighbors = [(0, 0), (-1, 0), (0, -1), (1, 0), (0, 1)];
             let mut prev_probs = Vec::new();
+            let mut movements = Vec::new();
 
-            for (mov_x, mov_y) in neighbors.iter() {
-                let (i, j) = (x + mov_x, y + mov_y);
-
+            for (i, j, p_a_b) in variant_predecessors(dp, variant, &self.kernels[variant], x, y) {
                 let p_b = dp.at_or(i, j, t - 1, variant, 0.0).unwrap();
-                let p_a = dp.at_or(x, y, t, variant, 0.0).unwrap();
-                let p_a_b = self.kernels[variant].at(i - x, j - y);
 
-                prev_probs.push((p_a_b * p_b) / p_a);
+                prev_probs.push(p_a_b * p_b);
+                movements.push((i - x, j - y));
             }
 
             let direction = match WeightedIndex::new(prev_probs) {
-                Ok(dist) => dist.sample(&mut rng),
+                Ok(dist) => dist.sample(rng),
                 Err(WeightedError::AllWeightsZero) => return Err(WalkerError::InconsistentPath),
                 _ => return Err(WalkerError::RandomDistributionError),
             };
+            let (mov_x, mov_y) = movements[direction];
 
-            last_direction = direction;
-
-            match direction {
-                1 => x -= 1,
-                2 => y -= 1,
-                3 => x += 1,
-                4 => y += 1,
-                _ => (),
+            if !neighbors.contains(&(mov_x, mov_y)) {
+                return Err(WalkerError::InconsistentPath);
             }
+
+            variant = Self::variant(mov_x, mov_y);
+
+            x += mov_x;
+            y += mov_y;
         }
 
         path.reverse();
@@ -112,3 +120,117 @@ impl Walker for CorrelatedWalker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::correlated_rw::CorrelatedRwGenerator;
+    use crate::kernel::Kernel;
+    use crate::lattice::Lattice;
+    use crate::walker::correlated::CorrelatedWalker;
+    use crate::walker::{Walker, WalkerError};
+
+    fn pool(kernels: &[Kernel]) -> DynamicProgramPool {
+        let dps = kernels
+            .iter()
+            .map(|kernel| {
+                let mut dp = DynamicProgramBuilder::new()
+                    .simple()
+                    .time_limit(10)
+                    .kernel(kernel.clone())
+                    .build()
+                    .unwrap();
+
+                dp.compute();
+                dp.try_into().unwrap()
+            })
+            .collect();
+
+        DynamicProgramPool::Multiple(dps)
+    }
+
+    #[test]
+    fn test_correlated_walker() {
+        let kernels =
+            Kernel::multiple_from_generator(CorrelatedRwGenerator { persistence: 0.5 }).unwrap();
+        let dp = pool(&kernels);
+        let walker = CorrelatedWalker { kernels };
+        let mut last_steps = Vec::new();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..100 {
+            let walk = walker
+                .generate_path_with_rng(&dp, 0, 0, 6, &mut rng)
+                .unwrap();
+            let n = walk.len();
+
+            assert_eq!(n, 7);
+            assert_eq!(walk[0], (0, 0).into());
+            assert_eq!(walk[n - 1], (0, 0).into());
+
+            for i in 1..n {
+                let (dx, dy) = (walk[i].x - walk[i - 1].x, walk[i].y - walk[i - 1].y);
+
+                assert!(Lattice::Square.is_neighbor(dx as isize, dy as isize));
+            }
+
+            last_steps.push((walk[n - 1].x - walk[n - 2].x, walk[n - 1].y - walk[n - 2].y));
+        }
+
+        // The last step can lead in any direction
+        for step in Lattice::Square.neighbors() {
+            assert!(last_steps.contains(&(step.0 as i64, step.1 as i64)));
+        }
+    }
+
+    #[test]
+    fn test_correlated_walker_without_steps() {
+        let kernels =
+            Kernel::multiple_from_generator(CorrelatedRwGenerator { persistence: 0.5 }).unwrap();
+        let dp = pool(&kernels);
+        let walker = CorrelatedWalker { kernels };
+
+        assert_eq!(
+            walker.generate_path(&dp, 0, 0, 0).unwrap().0,
+            vec![(0, 0).into()]
+        );
+        assert!(matches!(
+            walker.generate_path(&dp, 1, 0, 0),
+            Err(WalkerError::NoPathExists)
+        ));
+    }
+
+    #[test]
+    fn test_correlated_walker_variants() {
+        let kernels =
+            Kernel::multiple_from_generator(CorrelatedRwGenerator { persistence: 0.8 }).unwrap();
+
+        // Moving backwards by (0, -1) reverses a step south and by (0, 1) a step north
+        assert_eq!(kernels[CorrelatedWalker::variant(0, -1)].at(0, 1), 0.8);
+        assert_eq!(kernels[CorrelatedWalker::variant(0, 1)].at(0, -1), 0.8);
+
+        for &(mov_x, mov_y) in Lattice::Square.neighbors().iter() {
+            let kernel = &kernels[CorrelatedWalker::variant(mov_x, mov_y)];
+
+            assert_eq!(kernel.at(-mov_x, -mov_y), 0.8);
+        }
+    }
+
+    #[test]
+    fn test_correlated_walker_unsupported_lattice() {
+        let kernels = vec![Kernel::from_entries(1, &[(1, -1, 1.0)])
+            .unwrap()
+            .with_lattice(Lattice::Hexagonal)];
+        let dp = pool(&kernels);
+        let walker = CorrelatedWalker { kernels };
+
+        assert!(matches!(
+            walker.generate_path(&dp, 5, -5, 5),
+            Err(WalkerError::UnsupportedLattice(Lattice::Hexagonal))
+        ));
+    }
+}
@@ -2,12 +2,18 @@ use log::{debug, error, trace};
 use num::Zero;
 use rand::distributions::{WeightedError, WeightedIndex};
 use rand::prelude::Distribution;
-use rand::Rng;
+use rand::{Rng, RngCore};
 
 use crate::dp::DynamicProgramPool;
 use crate::kernel::Kernel;
-use crate::walker::{Walk, Walker, WalkerError};
-
+use crate::walker::{variant_predecessors, Walk, Walker, WalkerError};
+
+/// A walker for correlated random walks making steps of a fixed Manhattan distance `step_size`.
+///
+/// The dynamic programs and kernels must be ordered by the fields at distance `step_size`, ordered
+/// by `x` and then by `y`. In each step, the variant is chosen by the step that follows it. Steps
+/// are sampled from the predecessors of the dynamic program of the variant if it is held in memory
+/// and from the kernel of the variant otherwise.
 pub struct CorrelatedFixedStepWalker {
     step_size: usize,
     kernels: Vec<Kernel>,
@@ -20,12 +26,13 @@ impl CorrelatedFixedStepWalker {
 }
 
 impl Walker for CorrelatedFixedStepWalker {
-    fn generate_path(
+    fn generate_path_with_rng(
         &self,
         dp: &DynamicProgramPool,
         to_x: isize,
         to_y: isize,
         time_steps: usize,
+        rng: &mut dyn RngCore,
     ) -> Result<Walk, WalkerError> {
         if matches!(dp, DynamicProgramPool::Single(_)) {
             return Err(WalkerError::RequiresMultipleDynamicPrograms);
@@ -45,7 +52,6 @@ impl Walker for CorrelatedFixedStepWalker {
         let step_size = self.step_size as isize;
         let mut path = Vec::new();
         let (mut x, mut y) = (to_x, to_y);
-        let mut rng = rand::thread_rng();
 
         // Compute possible fields with given distance from center
         let mut possible_fields = Vec::new();
@@ -59,56 +65,46 @@ impl Walker for CorrelatedFixedStepWalker {
         }
 
         // Check if any path exists leading to the given end point for any variant
-        if !(0..dp_qty).any(|i| !dp.at(to_x, to_y, time_steps, i).unwrap().is_zero()) {
+        let variants: Vec<usize> = (0..dp_qty)
+            .filter(|&i| !dp.at(to_x, to_y, time_steps, i).unwrap().is_zero())
+            .collect();
+
+        if variants.is_empty() {
             return Err(WalkerError::NoPathExists);
         }
 
-        path.push((x as i64, y as i64).into());
-
-        // Compute first (= last, because reconstructing backwards) step manually
-        let direction = rng.gen_range(0..possible_fields.len());
-
-        let (dx, dy) = possible_fields[direction];
-        x += dx;
-        y += dy;
-
-        let mut last_direction = direction;
+        // The step after the last one is unknown, so start with a random variant
+        let mut last_direction = variants[rng.gen_range(0..variants.len())];
         debug!("First direction: {}", last_direction);
 
-        for t in (2..time_steps).rev() {
+        for t in (1..=time_steps).rev() {
             debug!("Time step: {}", t);
 
             path.push((x as i64, y as i64).into());
 
             let mut prev_probs = Vec::new();
+            let mut movements = Vec::new();
 
-            for (i, j) in possible_fields.iter() {
-                // Check if the field is within bounds
-                // if x + i < 0 || x + i > (2 * time_steps + 1) as isize || y + j < 0 || y + j > (2 * time_steps + 1) as isize {
-                //     prev_probs.push(0.0);
-                //     continue;
-                // }
-
-                let (i, j) = (x + i, y + j);
+            for (i, j, p_a_b) in
+                variant_predecessors(dp, last_direction, &self.kernels[last_direction], x, y)
+            {
                 let p_b = dp.at_or(i, j, t - 1, last_direction, 0.0).unwrap();
-                let p_a = dp.at_or(x, y, t, last_direction, 0.0).unwrap();
-                let p_a_b = self.kernels[last_direction].at(i - x, j - y);
 
                 trace!(
-                    "i: {}, j: {}, p_b: {}, p_a: {}, p_a_b: {}, prob: {}",
+                    "i: {}, j: {}, p_b: {}, p_a_b: {}, prob: {}",
                     i,
                     j,
                     p_b,
-                    p_a,
                     p_a_b,
-                    (p_a_b * p_b) / p_a
+                    p_a_b * p_b
                 );
 
-                prev_probs.push((p_a_b * p_b) / p_a);
+                prev_probs.push(p_a_b * p_b);
+                movements.push((i - x, j - y));
             }
 
             let direction = match WeightedIndex::new(&prev_probs) {
-                Ok(dist) => dist.sample(&mut rng),
+                Ok(dist) => dist.sample(rng),
                 Err(WeightedError::AllWeightsZero) => {
                     error!("time step: {t}, x: {x}, y: {y}");
                     return Err(WalkerError::InconsistentPath);
@@ -119,14 +115,17 @@ impl Walker for CorrelatedFixedStepWalker {
                     return Err(WalkerError::RandomDistributionError);
                 }
             };
-            let (dx, dy) = possible_fields[direction];
+            let (dx, dy) = movements[direction];
             x += dx;
             y += dy;
-            last_direction = direction;
+            last_direction = possible_fields
+                .iter()
+                .position(|&field| field == (dx, dy))
+                .ok_or(WalkerError::InconsistentPath)?;
 
             debug!(
                 "Direction: {}, Movement: ({}, {}), New Position: ({}, {})",
-                direction, dx, dy, x, y
+                last_direction, dx, dy, x, y
             );
         }
 
@@ -144,3 +143,69 @@ impl Walker for CorrelatedFixedStepWalker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::Kernel;
+    use crate::walker::correlated_fixed_step::CorrelatedFixedStepWalker;
+    use crate::walker::Walker;
+
+    #[test]
+    fn test_correlated_fixed_step_walker() {
+        let ring = [
+            (-2, 0),
+            (-1, -1),
+            (-1, 1),
+            (0, -2),
+            (0, 2),
+            (1, -1),
+            (1, 1),
+            (2, 0),
+        ];
+
+        // Keeping the direction of the previous step is more likely than turning
+        let kernels: Vec<Kernel> = ring
+            .iter()
+            .map(|&(dx, dy)| {
+                let entries: Vec<_> = ring
+                    .iter()
+                    .map(|&(x, y)| (x, y, if (x, y) == (dx, dy) { 0.3 } else { 0.1 }))
+                    .collect();
+
+                Kernel::from_entries(2, &entries).unwrap()
+            })
+            .collect();
+
+        let dps = kernels
+            .iter()
+            .map(|kernel| {
+                let mut dp = DynamicProgramBuilder::new()
+                    .simple()
+                    .time_limit(8)
+                    .kernel(kernel.clone())
+                    .build()
+                    .unwrap();
+
+                dp.compute();
+                dp.try_into().unwrap()
+            })
+            .collect();
+        let dp = DynamicProgramPool::Multiple(dps);
+
+        let walker = CorrelatedFixedStepWalker::new(2, kernels);
+
+        for walk in walker.generate_paths_seeded(&dp, 10, 4, 2, 5, 42).unwrap() {
+            assert_eq!(walk.len(), 6);
+            assert_eq!(walk[0], (0, 0).into());
+            assert_eq!(walk[5], (4, 2).into());
+
+            for step in walk.0.windows(2) {
+                let (dx, dy) = (step[1].x - step[0].x, step[1].y - step[0].y);
+
+                assert_eq!(dx.abs() + dy.abs(), 2);
+            }
+        }
+    }
+}
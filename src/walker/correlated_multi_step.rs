@@ -2,36 +2,40 @@ use log::{debug, error, trace};
 use num::Zero;
 use rand::distributions::{WeightedError, WeightedIndex};
 use rand::prelude::Distribution;
-use rand::Rng;
+use rand::{Rng, RngCore};
 
-use crate::dp::simple::DynamicProgram;
 use crate::dp::DynamicProgramPool;
+use crate::kernel::correlated_sectors::HeadingSectors;
 use crate::kernel::Kernel;
-use crate::walker::{Walk, Walker, WalkerError};
-
+use crate::walker::{variant_predecessors, Walk, Walker, WalkerError};
+
+/// A walker for correlated random walks with kernels larger than 3x3.
+///
+/// The dynamic programs and kernels must be ordered by the variants of the given
+/// [`HeadingSectors`], e.g. as generated by a
+/// [`CorrelatedSectorsGenerator`](crate::kernel::correlated_sectors::CorrelatedSectorsGenerator).
+/// In each step, the variant is chosen by the heading of the step that follows it. Steps are
+/// sampled using the transition probabilities of the dynamic program of the variant if it is held
+/// in memory and using the kernel of the variant otherwise.
 pub struct CorrelatedMultiStepWalker {
-    max_step_size: usize,
     kernels: Vec<Kernel>,
-    directions_per_axis: usize,
+    sectors: HeadingSectors,
 }
 
 impl CorrelatedMultiStepWalker {
-    pub fn new(max_step_size: usize, kernels: Vec<Kernel>, directions_per_axis: usize) -> Self {
-        Self {
-            max_step_size,
-            kernels,
-            directions_per_axis,
-        }
+    pub fn new(kernels: Vec<Kernel>, sectors: HeadingSectors) -> Self {
+        Self { kernels, sectors }
     }
 }
 
 impl Walker for CorrelatedMultiStepWalker {
-    fn generate_path(
+    fn generate_path_with_rng(
         &self,
         dp: &DynamicProgramPool,
         to_x: isize,
         to_y: isize,
         time_steps: usize,
+        rng: &mut dyn RngCore,
     ) -> Result<Walk, WalkerError> {
         if matches!(dp, DynamicProgramPool::Single(_)) {
             return Err(WalkerError::RequiresMultipleDynamicPrograms);
@@ -48,70 +52,23 @@ impl Walker for CorrelatedMultiStepWalker {
             _ => return Err(WalkerError::RequiresMultipleDynamicPrograms),
         };
 
-        let max_step_size = self.max_step_size as isize;
         let mut path = Vec::new();
         let (mut x, mut y) = (to_x, to_y);
-        let mut rng = rand::thread_rng();
-
-        // Divide into grid sections
-        let mut sections = Vec::new();
-        let mut start = -max_step_size;
-        let section_size = (2 * max_step_size + 1) / self.directions_per_axis as isize;
-        let remainder = (2 * max_step_size + 1) % self.directions_per_axis as isize;
-
-        for _ in 0..self.directions_per_axis {
-            let end = start + section_size;
-            sections.push(start..end);
-            start = end;
-        }
-
-        if remainder != 0 {
-            let middle = sections.len() / 2;
-            let middle_section = &mut sections[middle];
-            middle_section.end += remainder;
-        }
-
-        debug!("Sections: {:?}", sections);
 
         // Check if any path exists leading to the given end point for any variant
-        if !(0..dp_qty).any(|i| !dp.at(to_x, to_y, time_steps, i).unwrap().is_zero()) {
-            return Err(WalkerError::NoPathExists);
-        }
-
-        path.push((x as i64, y as i64).into());
+        let variants: Vec<usize> = (0..dp_qty)
+            .filter(|&i| !dp.at(to_x, to_y, time_steps, i).unwrap().is_zero())
+            .collect();
 
-        // Compute first (= last, because reconstructing backwards) step manually
-        let direction = rng.gen_range(0..9);
-
-        match direction {
-            0 => {
-                x -= 1;
-                y -= 1;
-            }
-            1 => y -= 1,
-            2 => {
-                x += 1;
-                y -= 1;
-            }
-            3 => x -= 1,
-            4 => (),
-            5 => x += 1,
-            6 => {
-                x -= 1;
-                y += 1;
-            }
-            7 => y += 1,
-            8 => {
-                x += 1;
-                y += 1;
-            }
-            _ => unimplemented!(),
+        if variants.is_empty() {
+            return Err(WalkerError::NoPathExists);
         }
 
-        let mut last_direction = direction;
+        // The heading after the last step is unknown, so start with a random variant
+        let mut last_direction = variants[rng.gen_range(0..variants.len())];
         debug!("First direction: {}", last_direction);
 
-        for t in (1..time_steps).rev() {
+        for t in (1..=time_steps).rev() {
             debug!("Time step: {}", t);
 
             path.push((x as i64, y as i64).into());
@@ -119,27 +76,19 @@ impl Walker for CorrelatedMultiStepWalker {
             let mut prev_probs = Vec::new();
             let mut movements = Vec::new();
 
-            for i in x - max_step_size..=x + max_step_size {
-                for j in y - max_step_size..=y + max_step_size {
-                    let p_b = dp.at_or(i, j, t - 1, last_direction, 0.0).unwrap();
-                    let p_a = dp.at_or(x, y, t, last_direction, 0.0).unwrap();
-                    let p_a_b = self.kernels[last_direction].at(i - x, j - y);
-
-                    trace!(
-                        "p_b: {}, p_a: {}, p_a_b: {}, prob: {}",
-                        p_b,
-                        p_a,
-                        p_a_b,
-                        (p_a_b * p_b) / p_a
-                    );
-
-                    prev_probs.push((p_a_b * p_b) / p_a);
-                    movements.push((i - x, j - y));
-                }
+            for (i, j, p_a_b) in
+                variant_predecessors(dp, last_direction, &self.kernels[last_direction], x, y)
+            {
+                let p_b = dp.at_or(i, j, t - 1, last_direction, 0.0).unwrap();
+
+                trace!("p_b: {}, p_a_b: {}, prob: {}", p_b, p_a_b, p_a_b * p_b);
+
+                prev_probs.push(p_a_b * p_b);
+                movements.push((i - x, j - y));
             }
 
             let direction = match WeightedIndex::new(&prev_probs) {
-                Ok(dist) => dist.sample(&mut rng),
+                Ok(dist) => dist.sample(rng),
                 Err(WeightedError::AllWeightsZero) => {
                     error!("time step: {t}, x: {x}, y: {y}");
                     return Err(WalkerError::InconsistentPath);
@@ -157,16 +106,8 @@ impl Walker for CorrelatedMultiStepWalker {
 
             debug!("Movement: ({}, {})", dx, dy);
 
-            let row = sections
-                .iter()
-                .position(|section| section.contains(&dx))
-                .unwrap();
-            let column = sections
-                .iter()
-                .position(|section| section.contains(&dy))
-                .unwrap()
-                * self.directions_per_axis;
-            last_direction = row + column;
+            // The sampled movement leads backwards, so its heading is reversed
+            last_direction = self.sectors.variant(-dx, -dy);
             debug!("Last direction: {}", last_direction);
         }
 
@@ -184,3 +125,67 @@ impl Walker for CorrelatedMultiStepWalker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::correlated_sectors::{CorrelatedSectorsGenerator, HeadingSectors};
+    use crate::kernel::directional::{HeadingDistribution, StepLengthDistribution};
+    use crate::kernel::Kernel;
+    use crate::walker::correlated_multi_step::CorrelatedMultiStepWalker;
+    use crate::walker::Walker;
+
+    #[test]
+    fn test_correlated_multi_step_walker() {
+        let sectors = HeadingSectors::new(8);
+        let kernels = Kernel::multiple_from_generator(CorrelatedSectorsGenerator {
+            sectors,
+            heading_distribution: HeadingDistribution::VonMises { concentration: 2.0 },
+            step_length: StepLengthDistribution::Gamma {
+                shape: 2.0,
+                scale: 0.75,
+            },
+            size: 5,
+        })
+        .unwrap();
+
+        let dps = kernels
+            .iter()
+            .map(|kernel| {
+                let mut dp = DynamicProgramBuilder::new()
+                    .simple()
+                    .time_limit(10)
+                    .kernel(kernel.clone())
+                    .build()
+                    .unwrap();
+
+                dp.compute();
+                dp.try_into().unwrap()
+            })
+            .collect();
+        let dp = DynamicProgramPool::Multiple(dps);
+
+        let walker = CorrelatedMultiStepWalker::new(kernels, sectors);
+
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..5 {
+            let walk = walker
+                .generate_path_with_rng(&dp, 6, -3, 8, &mut rng)
+                .unwrap();
+
+            assert_eq!(walk.len(), 9);
+            assert_eq!(walk[0], (0, 0).into());
+            assert_eq!(walk[walk.len() - 1], (6, -3).into());
+
+            for i in 1..walk.len() {
+                assert!((walk[i].x - walk[i - 1].x).abs() <= 2);
+                assert!((walk[i].y - walk[i - 1].y).abs() <= 2);
+            }
+        }
+    }
+}
@@ -1,27 +1,88 @@
 //! Provides walkers used to generate random walks by using a dynamic program.
 
+pub mod backtracking;
 pub mod correlated;
 pub mod correlated_fixed_step;
 pub mod correlated_multi_step;
-pub mod land_cover;
-pub mod levy;
-pub mod multi_step;
-pub mod standard;
+pub mod most_probable;
+pub mod states;
 
+/// Declares modules holding deprecated aliases of the
+/// [`BacktrackingWalker`](crate::walker::backtracking::BacktrackingWalker), which the walkers for
+/// a single dynamic program sampled steps identically to.
+macro_rules! deprecated_walkers {
+    ($($module:ident::$walker:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Provides the deprecated `", stringify!($walker), "`.")]
+            pub mod $module {
+                use crate::walker::backtracking::BacktrackingWalker;
+
+                /// A deprecated alias of the [`BacktrackingWalker`].
+                #[deprecated(since = "0.2.0", note = "use `BacktrackingWalker` instead")]
+                pub type $walker = BacktrackingWalker;
+
+                /// Allows to keep using the alias as a value, e.g. `Box::new(StandardWalker)`.
+                #[deprecated(since = "0.2.0", note = "use `BacktrackingWalker` instead")]
+                #[allow(non_upper_case_globals)]
+                pub const $walker: BacktrackingWalker = BacktrackingWalker;
+            }
+        )*
+    };
+}
+
+deprecated_walkers!(
+    land_cover::LandCoverWalker,
+    levy::LevyWalker,
+    multi_step::MultiStepWalker,
+    standard::StandardWalker,
+);
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use rayon::prelude::*;
 use thiserror::Error;
 
+use crate::dp::simple::DynamicProgram;
 use crate::dp::DynamicProgramPool;
+use crate::kernel::Kernel;
+use crate::lattice::Lattice;
 use crate::walk::Walk;
 
-pub trait Walker {
+/// A walker generating random walks ending at a given point from a dynamic program.
+///
+/// All random numbers are drawn from the random number generator passed to
+/// [`generate_path_with_rng()`](Walker::generate_path_with_rng), so walks can be reproduced using
+/// a seeded generator such as [`StdRng`](rand::rngs::StdRng).
+/// [`generate_path()`](Walker::generate_path) uses the thread-local generator.
+///
+/// Multiple walks are generated in parallel by [`generate_paths()`](Walker::generate_paths) and
+/// [`generate_paths_seeded()`](Walker::generate_paths_seeded), which is why walkers must be
+/// [`Sync`].
+///
+/// A walk generated for `time_steps` time steps contains `time_steps + 1` points, starting at the
+/// origin and ending at `(to_x, to_y)`. For `time_steps == 0`, it only contains the origin.
+pub trait Walker: Send + Sync {
     fn generate_path(
         &self,
         dp: &DynamicProgramPool,
         to_x: isize,
         to_y: isize,
         time_steps: usize,
+    ) -> Result<Walk, WalkerError> {
+        self.generate_path_with_rng(dp, to_x, to_y, time_steps, &mut rand::thread_rng())
+    }
+
+    fn generate_path_with_rng(
+        &self,
+        dp: &DynamicProgramPool,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        rng: &mut dyn RngCore,
     ) -> Result<Walk, WalkerError>;
 
+    /// Generates `qty` walks in parallel using a random master seed, see
+    /// [`generate_paths_seeded()`](Walker::generate_paths_seeded).
     fn generate_paths(
         &self,
         dp: &DynamicProgramPool,
@@ -29,11 +90,48 @@ pub trait Walker {
         to_x: isize,
         to_y: isize,
         time_steps: usize,
+    ) -> Result<Vec<Walk>, WalkerError> {
+        let seed = rand::thread_rng().gen();
+
+        self.generate_paths_seeded(dp, qty, to_x, to_y, time_steps, seed)
+    }
+
+    /// Generates `qty` walks in parallel. The `i`-th walk is generated using a random number
+    /// generator seeded with [`walk_seed(seed, i)`](walk_seed), so the walks and their order only
+    /// depend on `seed`. Returns an error if any walk cannot be generated.
+    fn generate_paths_seeded(
+        &self,
+        dp: &DynamicProgramPool,
+        qty: usize,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        seed: u64,
+    ) -> Result<Vec<Walk>, WalkerError> {
+        (0..qty)
+            .into_par_iter()
+            .map(|i| {
+                let mut rng = StdRng::seed_from_u64(walk_seed(seed, i as u64));
+
+                self.generate_path_with_rng(dp, to_x, to_y, time_steps, &mut rng)
+            })
+            .collect()
+    }
+
+    /// Generates `qty` walks sequentially drawing all random numbers from `rng`.
+    fn generate_paths_with_rng(
+        &self,
+        dp: &DynamicProgramPool,
+        qty: usize,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        rng: &mut dyn RngCore,
     ) -> Result<Vec<Walk>, WalkerError> {
         let mut paths = Vec::new();
 
         for _ in 0..qty {
-            paths.push(self.generate_path(dp, to_x, to_y, time_steps)?);
+            paths.push(self.generate_path_with_rng(dp, to_x, to_y, time_steps, rng)?);
         }
 
         Ok(paths)
@@ -42,6 +140,52 @@ pub trait Walker {
     fn name(&self, short: bool) -> String;
 }
 
+/// Derives the seed of the `index`-th walk from a master `seed`.
+///
+/// Seeding each walk separately makes it independent of the order in which walks are generated,
+/// so the same master seed always leads to the same walks, even when generating them in parallel.
+pub fn walk_seed(seed: u64, index: u64) -> u64 {
+    // SplitMix64 finalizer
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+    z ^ (z >> 31)
+}
+
+/// Returns all fields `(i, j)` from which `(x, y)` can be reached in a single step in the dynamic
+/// program of the given `variant`, together with the probability `p` of that step as `(i, j, p)`.
+///
+/// Dynamic programs held in memory use their own
+/// [`TransitionModel`](crate::dp::transition::TransitionModel), see
+/// [`DynamicProgram::predecessors()`] and [`DynamicProgram::transition_probability()`]. Dynamic
+/// programs read from disk only store their tables, so the non-zero entries of `kernel` are used
+/// instead.
+pub(crate) fn variant_predecessors(
+    dp: &DynamicProgramPool,
+    variant: usize,
+    kernel: &Kernel,
+    x: isize,
+    y: isize,
+) -> Vec<(isize, isize, f64)> {
+    let transitions = |dp: &DynamicProgram| {
+        dp.predecessors(x, y)
+            .map(|(i, j)| (i, j, dp.transition_probability(i, j, x, y)))
+            .collect()
+    };
+
+    match dp {
+        DynamicProgramPool::Single(dp) => transitions(dp),
+        DynamicProgramPool::Multiple(dps) => transitions(&dps[variant]),
+        DynamicProgramPool::MultipleFromDisk(_) => kernel
+            .support()
+            .into_iter()
+            .filter(|(_, _, p)| *p != 0.0)
+            .map(|(dx, dy, p)| (x - dx, y - dy, p))
+            .collect(),
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum WalkerError {
     #[error("the walker requires a single dynamic program but multiple were given")]
@@ -53,9 +197,171 @@ pub enum WalkerError {
     #[error("no path exists")]
     NoPathExists,
 
+    #[error("the number of time steps exceeds the time limit of the dynamic program")]
+    TimeLimitExceeded,
+
     #[error("found an inconsistent path, probably due to wrong settings in the dynamic program or walker")]
     InconsistentPath,
 
     #[error("error while computing random distribution")]
     RandomDistributionError,
+
+    #[error("the walker does not support kernels on a {0:?} lattice")]
+    UnsupportedLattice(Lattice),
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::dataset::point::XYPoint;
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::{DynamicProgramPool, DynamicPrograms};
+    use crate::kernel::correlated_rw::CorrelatedRwGenerator;
+    use crate::kernel::correlated_sectors::HeadingSectors;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::Kernel;
+    use crate::walker::backtracking::BacktrackingWalker;
+    use crate::walker::correlated::CorrelatedWalker;
+    use crate::walker::correlated_multi_step::CorrelatedMultiStepWalker;
+    use crate::walker::most_probable::MostProbableWalker;
+    use crate::walker::{walk_seed, Walker};
+    use crate::xy;
+
+    #[test]
+    fn test_seeded_walkers() {
+        let kernel = Kernel::from_generator(SimpleRwGenerator).unwrap();
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(20)
+            .kernel(kernel)
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let walker = BacktrackingWalker;
+        let generate = |seed| {
+            walker
+                .generate_paths_with_rng(&dp, 5, 3, -2, 20, &mut StdRng::seed_from_u64(seed))
+                .unwrap()
+        };
+
+        assert_eq!(generate(42), generate(42));
+        assert_ne!(generate(42), generate(43));
+
+        // Walks generated in parallel only depend on the seed and their index
+        let walks = walker.generate_paths_seeded(&dp, 8, 3, -2, 20, 42).unwrap();
+
+        for (i, walk) in walks.iter().enumerate() {
+            let mut rng = StdRng::seed_from_u64(walk_seed(42, i as u64));
+
+            assert_eq!(
+                walk,
+                &walker
+                    .generate_path_with_rng(&dp, 3, -2, 20, &mut rng)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_walkers() {
+        use crate::walker::land_cover::LandCoverWalker;
+        use crate::walker::levy::LevyWalker;
+        use crate::walker::multi_step::MultiStepWalker;
+        use crate::walker::standard::StandardWalker;
+
+        let walkers: Vec<Box<dyn Walker>> = vec![
+            Box::new(StandardWalker),
+            Box::new(MultiStepWalker),
+            Box::new(LevyWalker),
+            Box::new(LandCoverWalker),
+        ];
+        let alias: StandardWalker = BacktrackingWalker;
+
+        for walker in walkers.iter() {
+            assert_eq!(walker.name(true), alias.name(true));
+        }
+    }
+
+    #[test]
+    fn test_walk_lengths() {
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let kernels =
+            Kernel::multiple_from_generator(CorrelatedRwGenerator { persistence: 0.5 }).unwrap();
+        let dps = DynamicProgramPool::Multiple(
+            kernels
+                .iter()
+                .map(|kernel| {
+                    let mut dp = DynamicProgramBuilder::new()
+                        .simple()
+                        .time_limit(10)
+                        .kernel(kernel.clone())
+                        .build()
+                        .unwrap();
+
+                    dp.compute();
+                    dp.try_into().unwrap()
+                })
+                .collect(),
+        );
+
+        let walkers: Vec<(Box<dyn Walker>, &DynamicProgramPool)> = vec![
+            (Box::new(BacktrackingWalker), &dp),
+            (Box::new(MostProbableWalker), &dp),
+            (
+                Box::new(CorrelatedWalker {
+                    kernels: kernels.clone(),
+                }),
+                &dps,
+            ),
+            (
+                Box::new(CorrelatedMultiStepWalker::new(
+                    kernels,
+                    HeadingSectors::new(4),
+                )),
+                &dps,
+            ),
+        ];
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for (walker, dp) in walkers.iter() {
+            let walk = walker
+                .generate_path_with_rng(dp, 3, -1, 6, &mut rng)
+                .unwrap();
+
+            assert_eq!(walk.len(), 7);
+            assert_eq!(walk[0], xy!(0, 0));
+            assert_eq!(walk[6], xy!(3, -1));
+
+            let walk = walker
+                .generate_path_with_rng(dp, 0, 0, 0, &mut rng)
+                .unwrap();
+
+            assert_eq!(walk.0, vec![xy!(0, 0)]);
+        }
+    }
+
+    #[test]
+    fn test_walk_seed() {
+        let seeds: Vec<u64> = (0..100).map(|i| walk_seed(42, i)).collect();
+
+        assert_eq!(
+            seeds,
+            (0..100).map(|i| walk_seed(42, i)).collect::<Vec<_>>()
+        );
+        assert!((1..100).all(|i| !seeds[..i].contains(&seeds[i])));
+        assert_ne!(walk_seed(42, 0), walk_seed(43, 0));
+    }
 }
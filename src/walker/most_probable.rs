@@ -0,0 +1,335 @@
+//! Provides a walker that computes the most probable paths instead of random samples.
+//!
+//! The [`MostProbableWalker`] uses the same transition probabilities as the dynamic program it is
+//! given, including barriers, and computes the path with the highest probability from the origin
+//! to a given end point in a given number of time steps (Viterbi algorithm). Using
+//! [`most_probable_paths()`](MostProbableWalker::most_probable_paths), the `k` most probable
+//! distinct paths can be computed as well.
+
+use rand::RngCore;
+
+use crate::dp::{DynamicProgramPool, DynamicPrograms};
+use crate::walk::Walk;
+use crate::walker::{Walker, WalkerError};
+
+/// A walker that returns the most probable path between the origin and an end point.
+pub struct MostProbableWalker;
+
+/// A partial path ending in a specific field at a specific time step.
+#[derive(Clone, Copy)]
+struct Entry {
+    log_probability: f64,
+    predecessor: (isize, isize),
+    rank: usize,
+}
+
+/// The partial paths of a single time step in a rectangular area of fields.
+struct Layer {
+    from: (isize, isize),
+    to: (isize, isize),
+    entries: Vec<Vec<Entry>>,
+}
+
+impl Layer {
+    fn new(from: (isize, isize), to: (isize, isize)) -> Self {
+        let size = ((to.0 - from.0 + 1) * (to.1 - from.1 + 1)).max(0) as usize;
+
+        Self {
+            from,
+            to,
+            entries: vec![Vec::new(); size],
+        }
+    }
+
+    fn index(&self, x: isize, y: isize) -> Option<usize> {
+        if x < self.from.0 || x > self.to.0 || y < self.from.1 || y > self.to.1 {
+            return None;
+        }
+
+        Some(((x - self.from.0) * (self.to.1 - self.from.1 + 1) + (y - self.from.1)) as usize)
+    }
+
+    fn get(&self, x: isize, y: isize) -> &[Entry] {
+        match self.index(x, y) {
+            Some(i) => &self.entries[i],
+            None => &[],
+        }
+    }
+}
+
+impl MostProbableWalker {
+    /// Computes the `k` most probable distinct paths from `(0, 0)` to `(to_x, to_y)` in
+    /// `time_steps` time steps, sorted by decreasing probability.
+    ///
+    /// Returns the paths together with their log-probabilities. Fewer than `k` paths are returned
+    /// if fewer paths exist.
+    pub fn most_probable_paths(
+        &self,
+        dp: &DynamicProgramPool,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        k: usize,
+    ) -> Result<Vec<(Walk, f64)>, WalkerError> {
+        let DynamicProgramPool::Single(dp) = dp else {
+            return Err(WalkerError::RequiresSingleDynamicProgram);
+        };
+
+        let (limit_neg, limit_pos) = dp.limits();
+
+        if time_steps > limit_pos as usize {
+            return Err(WalkerError::TimeLimitExceeded);
+        }
+
+        // Check if any path exists leading to the given end point
+        if k == 0 || dp.at_or(to_x, to_y, time_steps, 0.0) == 0.0 {
+            return Err(WalkerError::NoPathExists);
+        }
+
+        let radius = dp.max_step_size() as isize;
+
+        let mut first = Layer::new((0, 0), (0, 0));
+        first.entries[0].push(Entry {
+            log_probability: 0.0,
+            predecessor: (0, 0),
+            rank: 0,
+        });
+
+        let mut layers = vec![first];
+
+        for t in 1..=time_steps {
+            // Only fields that are reachable from the origin in t steps and from which the end
+            // point is reachable in the remaining steps can be part of a path
+            let remaining = (time_steps - t) as isize;
+            let from = (
+                limit_neg
+                    .max(-(t as isize) * radius)
+                    .max(to_x - remaining * radius),
+                limit_neg
+                    .max(-(t as isize) * radius)
+                    .max(to_y - remaining * radius),
+            );
+            let to = (
+                limit_pos
+                    .min(t as isize * radius)
+                    .min(to_x + remaining * radius),
+                limit_pos
+                    .min(t as isize * radius)
+                    .min(to_y + remaining * radius),
+            );
+
+            let previous = &layers[t - 1];
+            let mut layer = Layer::new(from, to);
+
+            for x in from.0..=to.0 {
+                for y in from.1..=to.1 {
+                    if dp.at(x, y, t) == 0.0 {
+                        continue;
+                    }
+
+                    let mut candidates = Vec::new();
+
+                    for predecessor in dp.predecessors(x, y) {
+                        let p = dp.transition_probability(predecessor.0, predecessor.1, x, y);
+
+                        if p == 0.0 {
+                            continue;
+                        }
+
+                        let log_p = p.ln();
+
+                        for (rank, entry) in previous
+                            .get(predecessor.0, predecessor.1)
+                            .iter()
+                            .enumerate()
+                        {
+                            candidates.push(Entry {
+                                log_probability: entry.log_probability + log_p,
+                                predecessor,
+                                rank,
+                            });
+                        }
+                    }
+
+                    candidates.sort_by(|a, b| b.log_probability.total_cmp(&a.log_probability));
+                    candidates.truncate(k);
+
+                    let index = layer.index(x, y).unwrap();
+                    layer.entries[index] = candidates;
+                }
+            }
+
+            layers.push(layer);
+        }
+
+        // Reconstruct paths backwards
+
+        let mut paths = Vec::new();
+
+        for (rank, entry) in layers[time_steps].get(to_x, to_y).iter().enumerate() {
+            let mut path = Vec::with_capacity(time_steps + 1);
+            let (mut x, mut y, mut rank) = (to_x, to_y, rank);
+
+            for t in (0..=time_steps).rev() {
+                path.push((x as i64, y as i64).into());
+
+                let current = layers[t].get(x, y)[rank];
+                (x, y) = current.predecessor;
+                rank = current.rank;
+            }
+
+            path.reverse();
+            paths.push((Walk(path), entry.log_probability));
+        }
+
+        if paths.is_empty() {
+            return Err(WalkerError::NoPathExists);
+        }
+
+        Ok(paths)
+    }
+}
+
+impl Walker for MostProbableWalker {
+    /// Returns the most probable path. No random numbers are drawn from `rng`.
+    fn generate_path_with_rng(
+        &self,
+        dp: &DynamicProgramPool,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        _rng: &mut dyn RngCore,
+    ) -> Result<Walk, WalkerError> {
+        let mut paths = self.most_probable_paths(dp, to_x, to_y, time_steps, 1)?;
+
+        Ok(paths.remove(0).0)
+    }
+
+    /// Returns the `qty` most probable distinct paths instead of `qty` copies of the same path.
+    fn generate_paths_with_rng(
+        &self,
+        dp: &DynamicProgramPool,
+        qty: usize,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        _rng: &mut dyn RngCore,
+    ) -> Result<Vec<Walk>, WalkerError> {
+        Ok(self
+            .most_probable_paths(dp, to_x, to_y, time_steps, qty)?
+            .into_iter()
+            .map(|(walk, _)| walk)
+            .collect())
+    }
+
+    /// Returns the `qty` most probable distinct paths. The seed is not used.
+    fn generate_paths_seeded(
+        &self,
+        dp: &DynamicProgramPool,
+        qty: usize,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        _seed: u64,
+    ) -> Result<Vec<Walk>, WalkerError> {
+        self.generate_paths_with_rng(dp, qty, to_x, to_y, time_steps, &mut rand::thread_rng())
+    }
+
+    fn name(&self, short: bool) -> String {
+        if short {
+            String::from("mpw")
+        } else {
+            String::from("Most Probable Walker")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::point::XYPoint;
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::DynamicPrograms;
+    use crate::kernel::biased_rw::BiasedRwGenerator;
+    use crate::kernel::simple_rw::SimpleRwGenerator;
+    use crate::kernel::{Direction, Kernel};
+    use crate::walk::Walk;
+    use crate::walker::most_probable::MostProbableWalker;
+    use crate::walker::{Walker, WalkerError};
+    use crate::xy;
+
+    #[test]
+    fn test_most_probable_straight() {
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let walk = MostProbableWalker.generate_path(&dp, 3, 0, 3).unwrap();
+
+        assert_eq!(walk, Walk(vec![xy!(0, 0), xy!(1, 0), xy!(2, 0), xy!(3, 0)]));
+    }
+
+    #[test]
+    fn test_most_probable_barrier() {
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(Kernel::from_generator(SimpleRwGenerator).unwrap())
+            .add_rect_barrier(xy!(1, -1), xy!(1, 1))
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let walk = MostProbableWalker.generate_path(&dp, 2, 0, 6).unwrap();
+
+        assert_eq!(walk.len(), 7);
+        assert!(walk.iter().all(|p| p.x != 1 || p.y.abs() > 1));
+
+        assert!(matches!(
+            MostProbableWalker.generate_path(&dp, 2, 0, 2),
+            Err(WalkerError::NoPathExists)
+        ));
+    }
+
+    #[test]
+    fn test_most_probable_top_k() {
+        let mut dp = DynamicProgramBuilder::new()
+            .simple()
+            .time_limit(10)
+            .kernel(
+                Kernel::from_generator(BiasedRwGenerator {
+                    probability: 0.4,
+                    direction: Direction::East,
+                })
+                .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        dp.compute();
+
+        let paths = MostProbableWalker
+            .most_probable_paths(&dp, 1, 0, 3, 5)
+            .unwrap();
+
+        assert_eq!(paths.len(), 5);
+
+        for i in 0..paths.len() {
+            assert_eq!(paths[i].0.len(), 4);
+
+            for j in i + 1..paths.len() {
+                assert_ne!(paths[i].0, paths[j].0);
+                assert!(paths[i].1 >= paths[j].1);
+            }
+        }
+
+        // Two steps east and one step west is more likely than any path staying or going north
+        // and south
+        assert!((paths[0].1 - (0.4f64.ln() * 2.0 + 0.15f64.ln())).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,209 @@
+//! Provides a walker for dynamic programs with hidden behavioral states.
+//!
+//! The [`StateWalker`] samples a path together with its sequence of behavioral states from a
+//! [`StateDynamicProgram`], conditioned on the end point. Each step is sampled backwards from the
+//! joint distribution of the previous field and the previous state, using the switching matrix and
+//! the transition probabilities of the state the walk switched into. The returned
+//! [`LabelledWalk`] contains `time_steps + 1` points, starting at the origin, and the state of each
+//! point, which is the state used for the step leading to it.
+
+use rand::distributions::{WeightedError, WeightedIndex};
+use rand::prelude::*;
+
+use crate::dp::states::StateDynamicProgram;
+use crate::walk::{LabelledWalk, Walk};
+use crate::walker::WalkerError;
+
+/// A walker sampling paths and behavioral states from a [`StateDynamicProgram`].
+pub struct StateWalker;
+
+impl StateWalker {
+    /// Samples a path from the origin to `(to_x, to_y)` in `time_steps` time steps together with
+    /// its state sequence.
+    pub fn generate_path(
+        &self,
+        dp: &StateDynamicProgram,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+    ) -> Result<LabelledWalk, WalkerError> {
+        self.generate_path_with_rng(dp, to_x, to_y, time_steps, &mut rand::thread_rng())
+    }
+
+    /// Samples a path like [`generate_path()`](StateWalker::generate_path) drawing all random
+    /// numbers from `rng`.
+    pub fn generate_path_with_rng(
+        &self,
+        dp: &StateDynamicProgram,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<LabelledWalk, WalkerError> {
+        if time_steps > dp.time_limit() {
+            return Err(WalkerError::TimeLimitExceeded);
+        }
+
+        // Sample the state at the end point
+        let end_probs: Vec<f64> = (0..dp.state_count())
+            .map(|state| dp.at_or(to_x, to_y, time_steps, state, 0.0))
+            .collect();
+
+        let mut state = match WeightedIndex::new(end_probs) {
+            Ok(dist) => dist.sample(rng),
+            Err(WeightedError::AllWeightsZero) => return Err(WalkerError::NoPathExists),
+            _ => return Err(WalkerError::RandomDistributionError),
+        };
+
+        let mut path = Vec::new();
+        let mut labels = Vec::new();
+        let (mut x, mut y) = (to_x, to_y);
+
+        for t in (1..=time_steps).rev() {
+            path.push((x as i64, y as i64).into());
+            labels.push(state);
+
+            let mut prev_probs = Vec::new();
+            let mut movements = Vec::new();
+
+            for prev_state in 0..dp.state_count() {
+                let p_switch = dp.switching_probability(prev_state, state);
+
+                for (i, j) in dp.state(state).predecessors(x, y) {
+                    let p_b = dp.at_or(i, j, t - 1, prev_state, 0.0);
+                    let p_a_b = dp.state(state).transition_probability(i, j, x, y);
+
+                    prev_probs.push(p_b * p_switch * p_a_b);
+                    movements.push((i, j, prev_state));
+                }
+            }
+
+            let choice = match WeightedIndex::new(prev_probs) {
+                Ok(dist) => dist.sample(rng),
+                Err(WeightedError::AllWeightsZero) => return Err(WalkerError::InconsistentPath),
+                _ => return Err(WalkerError::RandomDistributionError),
+            };
+
+            (x, y, state) = movements[choice];
+        }
+
+        path.push((x as i64, y as i64).into());
+        labels.push(state);
+
+        path.reverse();
+        labels.reverse();
+
+        Ok(LabelledWalk {
+            walk: Walk(path),
+            labels,
+        })
+    }
+
+    /// Samples `qty` paths, see [`generate_path()`](StateWalker::generate_path).
+    pub fn generate_paths(
+        &self,
+        dp: &StateDynamicProgram,
+        qty: usize,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+    ) -> Result<Vec<LabelledWalk>, WalkerError> {
+        self.generate_paths_with_rng(dp, qty, to_x, to_y, time_steps, &mut rand::thread_rng())
+    }
+
+    /// Samples `qty` paths, see [`generate_path_with_rng()`](StateWalker::generate_path_with_rng).
+    pub fn generate_paths_with_rng(
+        &self,
+        dp: &StateDynamicProgram,
+        qty: usize,
+        to_x: isize,
+        to_y: isize,
+        time_steps: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<LabelledWalk>, WalkerError> {
+        (0..qty)
+            .map(|_| self.generate_path_with_rng(dp, to_x, to_y, time_steps, rng))
+            .collect()
+    }
+
+    pub fn name(&self, short: bool) -> String {
+        if short {
+            String::from("stw")
+        } else {
+            String::from("State Walker")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::dp::builder::DynamicProgramBuilder;
+    use crate::dp::states::StateDynamicProgram;
+    use crate::kernel;
+    use crate::kernel::biased_rw::BiasedRwGenerator;
+    use crate::kernel::{Direction, Kernel};
+    use crate::walker::states::StateWalker;
+    use crate::walker::WalkerError;
+
+    #[test]
+    fn test_state_walker() {
+        let state = |kernel: Kernel| {
+            DynamicProgramBuilder::new()
+                .simple()
+                .time_limit(10)
+                .kernel(kernel)
+                .build()
+                .unwrap()
+        };
+        let resting = state(kernel!(0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0));
+        let travelling = state(
+            Kernel::from_generator(BiasedRwGenerator {
+                probability: 1.0,
+                direction: Direction::East,
+            })
+            .unwrap(),
+        );
+
+        let mut dp = StateDynamicProgram::try_new(
+            vec![resting, travelling],
+            vec![vec![0.7, 0.3], vec![0.4, 0.6]],
+            vec![0.5, 0.5],
+        )
+        .unwrap();
+
+        dp.compute();
+
+        let walks = StateWalker
+            .generate_paths_with_rng(&dp, 20, 3, 0, 6, &mut StdRng::seed_from_u64(42))
+            .unwrap();
+
+        for walk in walks.iter() {
+            assert_eq!(walk.len(), 7);
+            assert_eq!(walk.labels.len(), 7);
+            assert_eq!((walk.walk[0].x, walk.walk[0].y), (0, 0));
+            assert_eq!((walk.walk[6].x, walk.walk[6].y), (3, 0));
+
+            // Resting steps stay in place, travelling steps move east
+            for i in 1..walk.len() {
+                let dx = walk.walk[i].x - walk.walk[i - 1].x;
+
+                assert_eq!(dx, walk.labels[i] as i64);
+                assert_eq!(walk.walk[i].y, 0);
+            }
+
+            assert_eq!(walk.labels[1..].iter().sum::<usize>(), 3);
+        }
+
+        assert!(matches!(
+            StateWalker.generate_path(&dp, -1, 0, 6),
+            Err(WalkerError::NoPathExists)
+        ));
+        assert!(matches!(
+            StateWalker.generate_path(&dp, 3, 0, 11),
+            Err(WalkerError::TimeLimitExceeded)
+        ));
+    }
+}